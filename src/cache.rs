@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+use crate::utils;
+
+/// Return the value `compute` gives for the executable `cmd`, such as its version.
+///
+/// The value is stored on disk, keyed by the resolved path of the executable along
+/// with its modification time and size, and reused until the executable changes.
+/// Values are only stored when `compute` returns `Some`, and never for version
+/// manager shims, which pick the executable they run on every call.
+pub fn get_or_compute<F>(cmd: &str, compute: F) -> Option<String>
+where
+    F: FnOnce() -> Option<String>,
+{
+    let (cache, identity) = match (VersionCache::new(), BinaryIdentity::resolve(cmd)) {
+        (Some(cache), Some(identity)) => (cache, identity),
+        _ => return compute(),
+    };
+
+    if let Some(value) = cache.get(&identity) {
+        log::trace!("Using cached value for {:?}", identity.path);
+        return Some(value);
+    }
+
+    let value = compute()?;
+    if let Err(err) = cache.insert(identity, &value) {
+        log::debug!("Unable to write version cache: {}", err);
+    }
+    Some(value)
+}
+
+/// Remove every cached value.
+pub fn clear() -> io::Result<()> {
+    let dir = match cache_dir() {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    match fs::remove_dir_all(&dir) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => {
+            println!("Cleared {}", dir.display());
+            Ok(())
+        }
+    }
+}
+
+/// `$XDG_CACHE_HOME/starship`, or `~/.cache/starship` if `XDG_CACHE_HOME` isn't set.
+fn cache_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cache")))
+        .map(|dir| dir.join("starship"))
+}
+
+/// What identifies a particular build of an executable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct BinaryIdentity {
+    /// The path of the executable, with symlinks resolved
+    path: PathBuf,
+    modified_secs: u64,
+    modified_nanos: u32,
+    size: u64,
+}
+
+impl BinaryIdentity {
+    /// Find `cmd` in `PATH` and read the identity of the executable it resolves to.
+    fn resolve(cmd: &str) -> Option<Self> {
+        let path = env::var_os("PATH")?;
+        let executable = env::split_paths(&path)
+            .map(|dir| dir.join(cmd))
+            .find(|candidate| is_executable(candidate))?;
+
+        Self::from_path(&executable)
+    }
+
+    fn from_path(executable: &Path) -> Option<Self> {
+        // pyenv, asdf and their like put an unchanging script in a `shims`
+        // directory, which runs the version chosen by the environment or the
+        // current directory
+        if executable
+            .parent()
+            .is_some_and(|dir| dir.ends_with("shims"))
+        {
+            log::trace!("Not caching the shim {:?}", executable);
+            return None;
+        }
+
+        let path = fs::canonicalize(executable).ok()?;
+        let metadata = fs::metadata(&path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        Some(Self {
+            path,
+            modified_secs: modified.as_secs(),
+            modified_nanos: modified.subsec_nanos(),
+            size: metadata.len(),
+        })
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Without mode bits there's no telling which file in `PATH` would run, so no
+/// executable is found and nothing is cached.
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    // TOML requires plain values to come before tables
+    value: String,
+    identity: BinaryIdentity,
+}
+
+/// The file holding cached values, keyed by executable path.
+struct VersionCache {
+    path: PathBuf,
+}
+
+impl VersionCache {
+    fn new() -> Option<Self> {
+        Some(Self::in_dir(cache_dir()?))
+    }
+
+    fn in_dir(dir: PathBuf) -> Self {
+        Self {
+            path: dir.join("versions.toml"),
+        }
+    }
+
+    fn load(&self) -> HashMap<String, CacheEntry> {
+        let contents = match utils::read_file(&self.path) {
+            Ok(contents) => contents,
+            Err(_) => return HashMap::new(),
+        };
+
+        toml::from_str(&contents).unwrap_or_else(|err| {
+            log::debug!("Ignoring unreadable version cache {:?}: {}", self.path, err);
+            HashMap::new()
+        })
+    }
+
+    /// The value stored for the executable, if it hasn't changed since.
+    fn get(&self, identity: &BinaryIdentity) -> Option<String> {
+        self.load()
+            .remove(&identity.path.to_string_lossy().into_owned())
+            .filter(|entry| entry.identity == *identity)
+            .map(|entry| entry.value)
+    }
+
+    /// Store a value for the executable, replacing any previous one.
+    ///
+    /// The file is written to a temporary file and renamed into place, so that
+    /// shells reading the cache at the same time never see a partial write. When
+    /// two shells write at once, one of their entries may be lost, which only
+    /// costs a recomputation.
+    fn insert(&self, identity: BinaryIdentity, value: &str) -> io::Result<()> {
+        let mut entries = self.load();
+        entries.insert(
+            identity.path.to_string_lossy().into_owned(),
+            CacheEntry {
+                value: value.to_string(),
+                identity,
+            },
+        );
+
+        let contents =
+            toml::to_string(&entries).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Modules write from several threads, so the process id isn't unique enough
+        static WRITE_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let temp_path = self.path.with_extension(format!(
+            "toml.{}-{}.tmp",
+            process::id(),
+            WRITE_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, &self.path).inspect_err(|_| {
+            fs::remove_file(&temp_path).ok();
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("starship-cache-{}-{}", name, process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_executable(path: &Path, contents: &str) {
+        fs::write(path, contents).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = test_dir("round-trip");
+        let tool = dir.join("tool");
+        write_executable(&tool, "#!/bin/sh\n");
+        let identity = BinaryIdentity::from_path(&tool).unwrap();
+
+        let cache = VersionCache::in_dir(dir.join("cache"));
+        assert_eq!(cache.get(&identity), None);
+
+        cache.insert(identity.clone(), "v1.0").unwrap();
+        assert_eq!(cache.get(&identity), Some("v1.0".to_string()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn changed_binary_misses_cache() {
+        let dir = test_dir("changed");
+        let tool = dir.join("tool");
+        write_executable(&tool, "#!/bin/sh\n");
+
+        let cache = VersionCache::in_dir(dir.join("cache"));
+        cache
+            .insert(BinaryIdentity::from_path(&tool).unwrap(), "v1.0")
+            .unwrap();
+
+        write_executable(&tool, "#!/bin/sh\necho 2\n");
+        assert_eq!(cache.get(&BinaryIdentity::from_path(&tool).unwrap()), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_cache_is_ignored() {
+        let dir = test_dir("corrupt");
+        let tool = dir.join("tool");
+        write_executable(&tool, "#!/bin/sh\n");
+        let identity = BinaryIdentity::from_path(&tool).unwrap();
+
+        let cache = VersionCache::in_dir(dir.clone());
+        fs::write(&cache.path, "not [valid toml").unwrap();
+        assert_eq!(cache.get(&identity), None);
+
+        cache.insert(identity.clone(), "v2.0").unwrap();
+        assert_eq!(cache.get(&identity), Some("v2.0".to_string()));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shims_are_not_cached() {
+        let dir = test_dir("shims");
+        fs::create_dir(dir.join("shims")).unwrap();
+        let shim = dir.join("shims").join("tool");
+        write_executable(&shim, "#!/bin/sh\n");
+
+        assert_eq!(BinaryIdentity::from_path(&shim), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn identity_resolves_symlinks() {
+        let dir = test_dir("symlink");
+        let tool = dir.join("tool");
+        write_executable(&tool, "#!/bin/sh\n");
+        std::os::unix::fs::symlink(&tool, dir.join("link")).unwrap();
+
+        let identity = BinaryIdentity::from_path(&dir.join("link")).unwrap();
+        assert_eq!(identity.path, fs::canonicalize(&tool).unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,241 @@
+use ansi_term::{Color, Style};
+use std::env;
+
+/// The colors that the terminal can show, which decides how the colors of
+/// styles are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// No styling at all, so no escape sequences are written
+    None,
+    /// The 8 standard colors, which most terminals also show in a bright variant
+    Basic,
+    /// The 256 colors of the xterm palette
+    Ansi256,
+    /// Any RGB color
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// The support named by the `--color` flag if given and not `auto`, or else
+    /// what `NO_COLOR`, `COLORTERM` and `TERM` tell about the terminal.
+    pub fn detect(flag: Option<&str>) -> Self {
+        if let Some(support) = flag.and_then(Self::from_name) {
+            return support;
+        }
+
+        Self::from_env(
+            env::var("NO_COLOR").ok().as_deref(),
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "never" => Some(ColorSupport::None),
+            "16" => Some(ColorSupport::Basic),
+            "256" => Some(ColorSupport::Ansi256),
+            "truecolor" => Some(ColorSupport::TrueColor),
+            _ => None,
+        }
+    }
+
+    fn from_env(no_color: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        // https://no-color.org asks for any non-empty value to turn colors off
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            return ColorSupport::None;
+        }
+        if let Some("truecolor" | "24bit") = colorterm {
+            return ColorSupport::TrueColor;
+        }
+
+        match term.unwrap_or_default() {
+            "dumb" => ColorSupport::None,
+            term if term.ends_with("-direct") => ColorSupport::TrueColor,
+            term if term.contains("256color") => ColorSupport::Ansi256,
+            _ => ColorSupport::Basic,
+        }
+    }
+
+    /// Replace the colors of `style` with the nearest ones the terminal can show.
+    pub fn apply(self, style: Style) -> Style {
+        let convert: fn(Color) -> Color = match self {
+            ColorSupport::None => return Style::default(),
+            ColorSupport::Basic => to_basic,
+            ColorSupport::Ansi256 => to_ansi256,
+            ColorSupport::TrueColor => return style,
+        };
+
+        Style {
+            foreground: style.foreground.map(convert),
+            background: style.background.map(convert),
+            ..style
+        }
+    }
+}
+
+const BASIC_COLORS: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Purple,
+    Color::Cyan,
+    Color::White,
+];
+
+/// The nearest of the 8 standard colors or their bright variants, which are
+/// the first 16 colors of the palette.
+fn to_basic(color: Color) -> Color {
+    let index = match color {
+        Color::Fixed(index) if index < 16 => index,
+        Color::Fixed(index) => nearest_palette_color(palette_rgb(index), 0..16),
+        Color::RGB(r, g, b) => nearest_palette_color((r, g, b), 0..16),
+        color => return color,
+    };
+
+    match BASIC_COLORS.get(usize::from(index)) {
+        Some(&color) => color,
+        // Bright colors have no name, but every terminal with 16 colors can show
+        // them by their index
+        None => Color::Fixed(index),
+    }
+}
+
+/// The nearest color of the 256-color palette, leaving out the first 16, whose
+/// values differ between terminals.
+fn to_ansi256(color: Color) -> Color {
+    match color {
+        Color::RGB(r, g, b) => Color::Fixed(nearest_palette_color((r, g, b), 16..=255)),
+        color => color,
+    }
+}
+
+fn nearest_palette_color<I>(rgb: (u8, u8, u8), indexes: I) -> u8
+where
+    I: Iterator<Item = u8>,
+{
+    let distance = |index: &u8| {
+        let (r, g, b) = palette_rgb(*index);
+        let channel = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        channel(r, rgb.0) + channel(g, rgb.1) + channel(b, rgb.2)
+    };
+
+    indexes.min_by_key(distance).unwrap_or(0)
+}
+
+/// The RGB value of a color of the xterm 256-color palette.
+fn palette_rgb(index: u8) -> (u8, u8, u8) {
+    const STANDARD: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    match index {
+        0..=15 => STANDARD[usize::from(index)],
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[usize::from(cube / 36)],
+                CUBE_LEVELS[usize::from(cube / 6 % 6)],
+                CUBE_LEVELS[usize::from(cube % 6)],
+            )
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_from_env() {
+        assert_eq!(
+            ColorSupport::from_env(Some("1"), Some("truecolor"), Some("xterm-256color")),
+            ColorSupport::None
+        );
+        assert_eq!(
+            ColorSupport::from_env(Some(""), Some("24bit"), Some("xterm")),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, None, Some("xterm-direct")),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, None, Some("screen-256color")),
+            ColorSupport::Ansi256
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, None, Some("linux")),
+            ColorSupport::Basic
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, None, Some("dumb")),
+            ColorSupport::None
+        );
+        assert_eq!(
+            ColorSupport::from_env(None, None, None),
+            ColorSupport::Basic
+        );
+    }
+
+    #[test]
+    fn flag_overrides_env() {
+        assert_eq!(ColorSupport::detect(Some("never")), ColorSupport::None);
+        assert_eq!(ColorSupport::detect(Some("256")), ColorSupport::Ansi256);
+    }
+
+    #[test]
+    fn truecolor_maps_to_nearest_palette_color() {
+        let style = Color::RGB(255, 135, 0).on(Color::RGB(10, 10, 10)).bold();
+
+        assert_eq!(
+            ColorSupport::Ansi256.apply(style),
+            Color::Fixed(208).on(Color::Fixed(232)).bold()
+        );
+        assert_eq!(
+            ColorSupport::Basic.apply(style),
+            Color::Yellow.on(Color::Black).bold()
+        );
+        assert_eq!(ColorSupport::TrueColor.apply(style), style);
+    }
+
+    #[test]
+    fn fixed_colors_map_to_basic_colors() {
+        assert_eq!(to_basic(Color::Fixed(1)), Color::Red);
+        assert_eq!(to_basic(Color::Fixed(12)), Color::Fixed(12));
+        assert_eq!(to_basic(Color::Fixed(46)), Color::Fixed(10));
+        assert_eq!(to_basic(Color::Fixed(28)), Color::Green);
+        assert_eq!(to_basic(Color::Cyan), Color::Cyan);
+        assert_eq!(to_ansi256(Color::Fixed(12)), Color::Fixed(12));
+    }
+
+    #[test]
+    fn no_color_removes_all_styling() {
+        assert_eq!(
+            ColorSupport::None.apply(Color::Red.bold().underline()),
+            Style::default()
+        );
+    }
+}
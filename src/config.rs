@@ -0,0 +1,355 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::module::{ALL_MODULES, SLOW_MODULES};
+use crate::utils;
+
+/// The modules rendered, in order, when the config file doesn't set `prompt_order`.
+pub const DEFAULT_PROMPT_ORDER: &[&str] = &[
+    "directory",
+    "git_branch",
+    "git_state",
+    "git_status",
+    "git_track",
+    "rust",
+    "golang",
+    "python",
+    "aws",
+    "plaio",
+    "tailscale",
+    "kube",
+    "env_var",
+    "cmd_duration",
+    "line_break",
+    "prompt",
+    "jobs",
+];
+
+/// The modules dropped, in order, from lines that don't fit in the terminal
+/// when the config file doesn't set `drop_order`.
+pub const DEFAULT_DROP_ORDER: &[&str] = &[
+    "env_var",
+    "kube",
+    "plaio",
+    "tailscale",
+    "aws",
+    "python",
+    "golang",
+    "rust",
+    "cmd_duration",
+    "git_track",
+];
+
+/// The user's configuration, read from `~/.config/starship.toml` or from the file
+/// pointed to by `STARSHIP_CONFIG`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The names of the modules to render, in the order they should appear.
+    pub prompt_order: Vec<String>,
+
+    /// The layout of the prompt as a format string, overriding `prompt_order`.
+    pub format: Option<String>,
+
+    /// The names of the modules to render on the right side of the terminal,
+    /// in the order they should appear. Empty by default.
+    pub right_prompt_order: Vec<String>,
+
+    /// The layout of the right prompt as a format string, overriding
+    /// `right_prompt_order`.
+    pub right_format: Option<String>,
+
+    /// How long to wait for modules, in milliseconds. Modules that take longer
+    /// are left out of the prompt.
+    pub render_timeout: u64,
+
+    /// How long modules wait for the commands they run, in milliseconds, before
+    /// killing them.
+    pub command_timeout: u64,
+
+    /// The modules to drop, first to last, from a line of the prompt that is
+    /// wider than the terminal. Modules that aren't listed are never dropped.
+    pub drop_order: Vec<String>,
+
+    /// The modules to shorten, first to last, from a line of the prompt that is
+    /// still wider than the terminal once every module in `drop_order` is gone.
+    pub shorten_order: Vec<String>,
+
+    /// Draw each module on its own background, joined to the next module by
+    /// `powerline_separator`.
+    pub powerline: bool,
+
+    /// The symbol between modules in powerline mode, drawn in the background
+    /// color of the module before it, on the background of the module after it.
+    pub powerline_separator: String,
+
+    /// Shown in place of slow modules until the shell has rendered them in the
+    /// background. An empty string hides them instead.
+    pub async_placeholder: String,
+
+    /// The palette to style the prompt with: `dark`, `light`, `solarized`, or the
+    /// name of a table in `palettes`. `STARSHIP_THEME` takes precedence.
+    pub palette: Option<String>,
+
+    /// User-defined palettes, mapping slot names such as `accent` to styles.
+    pub palettes: HashMap<String, HashMap<String, String>>,
+
+    /// The option tables of individual modules, keyed by module name.
+    #[serde(flatten)]
+    modules: toml::value::Table,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prompt_order: DEFAULT_PROMPT_ORDER
+                .iter()
+                .map(|&m| m.to_string())
+                .collect(),
+            format: None,
+            right_prompt_order: Vec::new(),
+            right_format: None,
+            render_timeout: 1000,
+            command_timeout: 500,
+            drop_order: DEFAULT_DROP_ORDER.iter().map(|&m| m.to_string()).collect(),
+            shorten_order: vec!["directory".to_string()],
+            powerline: false,
+            powerline_separator: "\u{e0b0}".to_string(),
+            async_placeholder: "…".to_string(),
+            palette: None,
+            palettes: HashMap::new(),
+            modules: toml::value::Table::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file, falling back to the built-in layout if it is missing
+    /// or can't be parsed.
+    pub fn initialize() -> Self {
+        let file_path = match config_path() {
+            Some(path) => path,
+            None => {
+                log::debug!("Unable to determine config file path, using defaults");
+                return Self::default();
+            }
+        };
+
+        let contents = match utils::read_file(&file_path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                log::debug!("No config file found at {:?}, using defaults", file_path);
+                return Self::default();
+            }
+            Err(err) => {
+                log::warn!("Unable to read config file {:?}: {}", file_path, err);
+                return Self::default();
+            }
+        };
+
+        Self::from_toml(&contents).unwrap_or_else(|err| {
+            log::warn!("Unable to parse config file {:?}: {}", file_path, err);
+            Self::default()
+        })
+    }
+
+    /// Parse a config from the contents of a TOML file, dropping any unknown
+    /// module from the lists of modules.
+    fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        let mut config: Self = toml::from_str(contents)?;
+
+        retain_known_modules(&mut config.prompt_order, "prompt_order");
+        retain_known_modules(&mut config.right_prompt_order, "right_prompt_order");
+        retain_known_modules(&mut config.drop_order, "drop_order");
+        retain_known_modules(&mut config.shorten_order, "shorten_order");
+
+        Ok(config)
+    }
+
+    /// How long modules wait for the commands they run before killing them.
+    pub fn command_timeout(&self) -> Duration {
+        Duration::from_millis(self.command_timeout)
+    }
+
+    /// Whether a module has been turned off with `disabled = true` in its table.
+    pub fn is_module_disabled(&self, module_name: &str) -> bool {
+        self.modules
+            .get(module_name)
+            .and_then(|table| table.get("disabled"))
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Whether a module is slow enough to be rendered in the background, which
+    /// can be changed with `slow = true` or `slow = false` in its table.
+    pub fn is_module_slow(&self, module_name: &str) -> bool {
+        self.modules
+            .get(module_name)
+            .and_then(|table| table.get("slow"))
+            .and_then(toml::Value::as_bool)
+            .unwrap_or_else(|| SLOW_MODULES.contains(&module_name))
+    }
+
+    /// Deserialize the option table of a module into its typed config.
+    ///
+    /// Options missing from the table keep their default values. If the table
+    /// can't be deserialized, the defaults are used for every option.
+    pub fn get_module_config<T>(&self, module_name: &str) -> T
+    where
+        T: DeserializeOwned + Default,
+    {
+        let table = match self.modules.get(module_name) {
+            Some(table) => table.clone(),
+            None => return T::default(),
+        };
+
+        table.try_into().unwrap_or_else(|err| {
+            log::warn!("Invalid options for module {:?}: {}", module_name, err);
+            T::default()
+        })
+    }
+}
+
+fn retain_known_modules(order: &mut Vec<String>, option_name: &str) {
+    order.retain(|name| {
+        let known = ALL_MODULES.contains(&name.as_str());
+        if !known {
+            log::warn!("Unknown module {:?} in {}", name, option_name);
+        }
+        known
+    });
+}
+
+/// The path of the config file, honouring `STARSHIP_CONFIG` if it is set.
+fn config_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("STARSHIP_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    dirs::home_dir().map(|home| home.join(".config/starship.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(default)]
+    struct TestConfig {
+        length: usize,
+        symbol: String,
+    }
+
+    impl Default for TestConfig {
+        fn default() -> Self {
+            Self {
+                length: 3,
+                symbol: "~".to_string(),
+            }
+        }
+    }
+
+    #[test]
+    fn empty_config_uses_default_order() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.prompt_order, DEFAULT_PROMPT_ORDER);
+    }
+
+    #[test]
+    fn prompt_order_drops_unknown_modules() {
+        let config = Config::from_toml(r#"prompt_order = ["rust", "nope", "prompt"]"#).unwrap();
+        assert_eq!(config.prompt_order, ["rust", "prompt"]);
+    }
+
+    #[test]
+    fn right_prompt_order_is_empty_by_default() {
+        assert!(Config::from_toml("").unwrap().right_prompt_order.is_empty());
+
+        let config =
+            Config::from_toml(r#"right_prompt_order = ["cmd_duration", "nope", "aws"]"#).unwrap();
+        assert_eq!(config.right_prompt_order, ["cmd_duration", "aws"]);
+    }
+
+    #[test]
+    fn layout_orders_drop_unknown_modules() {
+        let config = Config::from_toml("").unwrap();
+        assert_eq!(config.drop_order, DEFAULT_DROP_ORDER);
+        assert_eq!(config.shorten_order, ["directory"]);
+
+        let config = Config::from_toml(
+            r#"drop_order = ["aws", "nope"]
+shorten_order = []"#,
+        )
+        .unwrap();
+        assert_eq!(config.drop_order, ["aws"]);
+        assert!(config.shorten_order.is_empty());
+    }
+
+    #[test]
+    fn palettes_are_not_module_tables() {
+        let config = Config::from_toml(
+            r#"palette = "mine"
+
+[palettes.mine]
+accent = "bold #ff8800"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.palette.as_deref(), Some("mine"));
+        assert_eq!(config.palettes["mine"]["accent"], "bold #ff8800");
+        assert!(!config.modules.contains_key("palettes"));
+    }
+
+    #[test]
+    fn malformed_config_is_an_error() {
+        assert!(Config::from_toml("prompt_order = 3").is_err());
+        assert!(Config::from_toml("[directory").is_err());
+    }
+
+    #[test]
+    fn format_is_optional() {
+        assert_eq!(Config::from_toml("").unwrap().format, None);
+
+        let config = Config::from_toml(r#"format = "$directory$prompt""#).unwrap();
+        assert_eq!(config.format.as_deref(), Some("$directory$prompt"));
+    }
+
+    #[test]
+    fn module_disabled() {
+        let config = Config::from_toml("[rust]\ndisabled = true").unwrap();
+        assert!(config.is_module_disabled("rust"));
+        assert!(!config.is_module_disabled("golang"));
+    }
+
+    #[test]
+    fn module_slow() {
+        let config = Config::from_toml("[rust]\nslow = true\n[git_status]\nslow = false").unwrap();
+        assert!(config.is_module_slow("rust"));
+        assert!(!config.is_module_slow("git_status"));
+        assert!(config.is_module_slow("git_track"));
+        assert!(!config.is_module_slow("directory"));
+        assert!(!config.is_module_slow("golang"));
+    }
+
+    #[test]
+    fn module_config_fills_in_defaults() {
+        let config = Config::from_toml("[test]\nlength = 5").unwrap();
+        let module_config: TestConfig = config.get_module_config("test");
+        assert_eq!(module_config.length, 5);
+        assert_eq!(module_config.symbol, "~");
+    }
+
+    #[test]
+    fn invalid_module_config_uses_defaults() {
+        let config = Config::from_toml("[test]\nlength = \"five\"\nsymbol = \"$\"").unwrap();
+        let module_config: TestConfig = config.get_module_config("test");
+        assert_eq!(module_config.length, 3);
+        assert_eq!(module_config.symbol, "~");
+    }
+}
@@ -1,4 +1,8 @@
+use crate::color::ColorSupport;
+use crate::config::Config;
 use crate::module::Module;
+use crate::shell::Shell;
+use crate::theme::{self, Palette};
 
 use clap::ArgMatches;
 use git2::{Repository, RepositoryState};
@@ -19,13 +23,34 @@ pub struct Context<'a> {
     pub current_dir: PathBuf,
 
     /// A vector containing the full paths of all the files in `current_dir`.
+    ///
+    /// Modules render on separate threads, so this and `repo` are sync `OnceCell`s:
+    /// the first module to ask computes the value while the others wait for it.
     dir_files: OnceCell<Vec<PathBuf>>,
 
     /// Properties to provide to modules.
     pub properties: HashMap<&'a str, String>,
 
+    /// The user's configuration, or the built-in defaults if none was found.
+    pub config: Config,
+
+    /// The shell that the prompt is printed for.
+    pub shell: Shell,
+
+    /// The colors that the terminal can show.
+    pub color_support: ColorSupport,
+
+    /// The palette that the palette slots of styles are looked up in.
+    pub palette: Palette,
+
     /// Private field to store Git information for modules who need it
     repo: OnceCell<Repo>,
+
+    /// How long it took to compute `repo`, once computed.
+    repo_duration: OnceCell<Duration>,
+
+    /// How long it took to compute `dir_files`, once computed.
+    dir_files_duration: OnceCell<Duration>,
 }
 
 impl<'a> Context<'a> {
@@ -64,11 +89,23 @@ impl<'a> Context<'a> {
         // TODO: Currently gets the physical directory. Get the logical directory.
         let current_dir = Context::expand_tilde(dir.into());
 
+        let shell = Shell::detect(properties.get("shell").map(String::as_str));
+        let color_support = ColorSupport::detect(properties.get("color").map(String::as_str));
+
+        let config = Config::initialize();
+        let palette = theme::select(&config);
+
         Context {
             properties,
+            config,
+            shell,
+            color_support,
+            palette,
             current_dir,
             dir_files: OnceCell::new(),
             repo: OnceCell::new(),
+            repo_duration: OnceCell::new(),
+            dir_files_duration: OnceCell::new(),
         }
     }
 
@@ -83,7 +120,9 @@ impl<'a> Context<'a> {
 
     /// Create a new module
     pub fn new_module(&self) -> Module {
-        Module::new()
+        let mut module = Module::new();
+        module.set_color_support(self.color_support);
+        module
     }
 
     // returns a new ScanDir struct with reference to current dir_files of context
@@ -98,9 +137,11 @@ impl<'a> Context<'a> {
     }
 
     /// Will lazily get repo root and branch when a module requests it.
+    #[allow(clippy::redundant_closure)]
     pub fn get_repo(&self) -> Result<&Repo, std::io::Error> {
         self.repo
             .get_or_try_init(|| -> Result<Repo, std::io::Error> {
+                let start_time = Instant::now();
                 let repository = Repository::discover(&self.current_dir).ok();
                 let branch = repository
                     .as_ref()
@@ -109,6 +150,7 @@ impl<'a> Context<'a> {
                     .as_ref()
                     .and_then(|repo| repo.workdir().map(Path::to_path_buf));
                 let state = repository.as_ref().map(|repo| repo.state());
+                self.repo_duration.set(start_time.elapsed()).ok();
 
                 Ok(Repo {
                     branch,
@@ -119,11 +161,13 @@ impl<'a> Context<'a> {
     }
 
     pub fn get_dir_files(&self) -> Result<&Vec<PathBuf>, std::io::Error> {
-        let start_time = Instant::now();
-        let scan_timeout = Duration::from_millis(30);
-
         self.dir_files
             .get_or_try_init(|| -> Result<Vec<PathBuf>, std::io::Error> {
+                // Start timing here rather than on entry, as the calling thread
+                // may have been blocked while another one ran a failed scan.
+                let start_time = Instant::now();
+                let scan_timeout = Duration::from_millis(30);
+
                 let dir_files = fs::read_dir(&self.current_dir)?
                     .enumerate()
                     .take_while(|(n, _item)| {
@@ -133,13 +177,24 @@ impl<'a> Context<'a> {
                     .map(|entry| entry.path())
                     .collect::<Vec<PathBuf>>();
 
-                log::trace!(
-                    "Building a vector of directory files took {:?}",
-                    Instant::now().duration_since(start_time)
-                );
+                let duration = Instant::now().duration_since(start_time);
+                log::trace!("Building a vector of directory files took {:?}", duration);
+                self.dir_files_duration.set(duration).ok();
                 Ok(dir_files)
             })
     }
+
+    /// The time spent on work shared between modules, such as `get_repo`, for
+    /// the work that has been done so far.
+    pub fn shared_durations(&self) -> Vec<(&'static str, Duration)> {
+        [
+            ("get_repo", self.repo_duration.get()),
+            ("get_dir_files", self.dir_files_duration.get()),
+        ]
+        .iter()
+        .filter_map(|&(name, duration)| Some((name, *duration?)))
+        .collect()
+    }
 }
 
 pub struct Repo {
@@ -183,18 +238,29 @@ impl<'a> ScanDir<'a> {
 
     /// based on the current Pathbuf check to see
     /// if any of this criteria match or exist and returning a boolean
+    #[cfg(test)]
     pub fn is_match(&self) -> bool {
-        self.dir_files.iter().any(|path| {
-            if path.is_dir() {
-                path_has_name(path, self.folders)
-            } else {
-                path_has_name(path, self.files) || has_extension(path, self.extensions)
-            }
-        })
+        self.find_match().is_some()
+    }
+
+    /// returns the first file or folder matching the criteria, so that modules
+    /// can tell why they are shown
+    pub fn find_match(&self) -> Option<&'a Path> {
+        self.dir_files
+            .iter()
+            .find(|path| {
+                if path.is_dir() {
+                    path_has_name(path, self.folders)
+                } else {
+                    path_has_name(path, self.files) || has_extension(path, self.extensions)
+                }
+            })
+            .map(PathBuf::as_path)
     }
 }
 
 /// checks to see if the pathbuf matches a file or folder name
+#[allow(clippy::ptr_arg)]
 pub fn path_has_name<'a>(dir_entry: &PathBuf, names: &'a [&'a str]) -> bool {
     let found_file_or_folder_name = names.iter().find(|file_or_folder_name| {
         dir_entry
@@ -211,6 +277,7 @@ pub fn path_has_name<'a>(dir_entry: &PathBuf, names: &'a [&'a str]) -> bool {
 }
 
 /// checks if pathbuf doesn't start with a dot and matches any provided extension
+#[allow(clippy::ptr_arg, clippy::unnecessary_map_or)]
 pub fn has_extension<'a>(dir_entry: &PathBuf, extensions: &'a [&'a str]) -> bool {
     if let Some(file_name) = dir_entry.file_name() {
         if file_name.to_string_lossy().starts_with('.') {
@@ -238,6 +305,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_path_has_name() {
         let mut buf = PathBuf::from("/");
         let files = vec!["package.json"];
@@ -252,6 +320,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_has_extension() {
         let mut buf = PathBuf::from("/");
         let extensions = vec!["js"];
@@ -269,6 +338,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_criteria_scan_fails() {
         let failing_criteria = ScanDir {
             dir_files: &vec![PathBuf::new()],
@@ -292,6 +362,35 @@ mod tests {
     }
 
     #[test]
+    fn test_dir_files_shared_between_threads() {
+        let context = Context::new_with_dir(ArgMatches::default(), env!("CARGO_MANIFEST_DIR"));
+
+        let addresses: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| context.get_dir_files().unwrap() as *const _ as usize))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        // Every thread got the same, single scan of the directory
+        assert!(addresses.iter().all(|&address| address == addresses[0]));
+        assert_eq!(
+            context
+                .shared_durations()
+                .iter()
+                .map(|&(name, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["get_dir_files"]
+        );
+        assert!(context
+            .get_dir_files()
+            .unwrap()
+            .iter()
+            .any(|file| file.ends_with("Cargo.toml")));
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_criteria_scan_passes() {
         let passing_criteria = ScanDir {
             dir_files: &vec![PathBuf::from("package.json")],
@@ -301,5 +400,9 @@ mod tests {
         };
 
         assert_eq!(passing_criteria.is_match(), true);
+        assert_eq!(
+            passing_criteria.find_match(),
+            Some(Path::new("package.json"))
+        );
     }
 }
@@ -0,0 +1,75 @@
+use clap::ArgMatches;
+
+use crate::context::Context;
+use crate::module::Module;
+use crate::modules;
+use crate::print;
+
+/// Print the value of each module shown for the current directory, along with
+/// what it means and why it is shown. Modules of the left prompt come first,
+/// followed by those only in the right prompt.
+pub fn explain(args: ArgMatches) {
+    let context = Context::new(args);
+    let format = print::prompt_format(&context.config, &context.palette);
+    let right_format = print::right_prompt_format(&context.config, &context.palette);
+
+    let mut names: Vec<&str> = Vec::new();
+    for name in format
+        .variables()
+        .into_iter()
+        .chain(right_format.variables())
+    {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    for name in names {
+        if context.config.is_module_disabled(name) {
+            continue;
+        }
+
+        match modules::handle(name, &context) {
+            Some(module) if !module.get_value().is_empty() => {
+                print!("{}", describe_module(name, &module))
+            }
+            _ => log::debug!("Module {} is not shown", name),
+        }
+    }
+}
+
+/// Describe a module in the form:
+///
+/// ```text
+/// rust: "+Rust"
+///     The current directory is a Rust project
+///     Shown: Cargo.toml found
+/// ```
+fn describe_module(name: &str, module: &Module) -> String {
+    format!(
+        "{}: {:?}\n    {}\n    Shown: {}\n",
+        name,
+        module.get_value(),
+        module.get_description(),
+        module.get_reason()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_module_lists_value_description_and_reason() {
+        let mut module = Module::new();
+        module.append_segment_str("+Rust");
+        module
+            .set_description("The current directory is a Rust project")
+            .set_reason("Cargo.toml found");
+
+        assert_eq!(
+            describe_module("rust", &module),
+            "rust: \"+Rust\"\n    The current directory is a Rust project\n    Shown: Cargo.toml found\n"
+        );
+    }
+}
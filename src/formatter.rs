@@ -0,0 +1,469 @@
+use ansi_term::Style;
+use std::fmt;
+
+use crate::style::parse_style_string;
+use crate::theme::Palette;
+
+/// A single element of a parsed format string.
+#[derive(Debug, PartialEq)]
+pub enum FormatElement {
+    /// Literal text, printed as-is.
+    Text(String),
+
+    /// A `$name` variable, replaced by the output of the module with that name.
+    Variable(String),
+
+    /// A `[text](style)` group, whose literal text is printed with `style`.
+    Styled(Vec<FormatElement>, Style),
+
+    /// A `(text)` group, which disappears when every variable inside it is empty.
+    Conditional(Vec<FormatElement>),
+}
+
+/// The layout of the prompt, as described by a format string such as
+/// `"$directory$git_branch$git_status\n$prompt"`.
+///
+/// Format strings support the following syntax:
+///   - `$name` – The output of the module called `name`
+///   - `[text](style)` – Literal text printed with the given style
+///   - `(text)` – A group that is only shown if a variable inside it has a value
+///   - `\` – Escapes the next character, e.g. `\$` prints a literal `$`
+#[derive(Debug, PartialEq)]
+pub struct PromptFormat {
+    elements: Vec<FormatElement>,
+}
+
+impl PromptFormat {
+    /// Parse a format string, reporting the column of the first syntax error.
+    /// Palette slots in the styles of `[text](style)` groups are looked up in
+    /// `palette`.
+    pub fn parse(format: &str, palette: &Palette) -> Result<Self, FormatError> {
+        let mut parser = Parser {
+            chars: format.chars().collect(),
+            pos: 0,
+            palette,
+        };
+        let elements = parser.parse_elements(None)?;
+
+        Ok(Self { elements })
+    }
+
+    /// Create a format that prints the given modules one after another.
+    pub fn from_modules(modules: &[String]) -> Self {
+        let elements = modules
+            .iter()
+            .map(|module| FormatElement::Variable(module.clone()))
+            .collect();
+
+        Self { elements }
+    }
+
+    /// The names of the variables used by the format, in order of first appearance.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut variables = Vec::new();
+        collect_variables(&self.elements, &mut variables);
+        variables
+    }
+
+    /// Replace the style of every `[text](style)` group with `map(style)`.
+    pub fn map_styles<F>(&mut self, map: F)
+    where
+        F: Fn(Style) -> Style,
+    {
+        map_element_styles(&mut self.elements, &map);
+    }
+
+    /// Render the format, using `get_variable` to get the value of each variable.
+    ///
+    /// A variable is empty if `get_variable` returns `None` or an empty string.
+    pub fn render<F>(&self, get_variable: F) -> String
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        self.render_pieces(get_variable)
+            .into_iter()
+            .map(|piece| match piece.style {
+                // Variables are the output of modules, which style themselves
+                Some(style) if piece.variable.is_none() => style.paint(piece.text).to_string(),
+                _ => piece.text,
+            })
+            .collect()
+    }
+
+    /// Render the format like `render`, but keep the output of each variable as
+    /// a separate piece, so that it can be laid out.
+    pub fn render_pieces<F>(&self, get_variable: F) -> Vec<Piece>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let mut pieces = Vec::new();
+        render_elements(&self.elements, None, &get_variable, &mut pieces);
+        pieces
+    }
+}
+
+/// A piece of rendered output, either the value of a variable or literal text.
+#[derive(Debug, PartialEq)]
+pub struct Piece {
+    /// The name of the variable, or `None` for literal text.
+    pub variable: Option<String>,
+
+    /// The rendered text, without the style of its group.
+    pub text: String,
+
+    /// The style of the `[text](style)` group the piece is in, if any.
+    pub style: Option<Style>,
+}
+
+fn collect_variables<'a>(elements: &'a [FormatElement], variables: &mut Vec<&'a str>) {
+    for element in elements {
+        match element {
+            FormatElement::Text(_) => {}
+            FormatElement::Variable(name) => {
+                if !variables.contains(&name.as_str()) {
+                    variables.push(name);
+                }
+            }
+            FormatElement::Styled(children, _) | FormatElement::Conditional(children) => {
+                collect_variables(children, variables)
+            }
+        }
+    }
+}
+
+fn map_element_styles<F>(elements: &mut [FormatElement], map: &F)
+where
+    F: Fn(Style) -> Style,
+{
+    for element in elements {
+        match element {
+            FormatElement::Text(_) | FormatElement::Variable(_) => {}
+            FormatElement::Styled(children, style) => {
+                *style = map(*style);
+                map_element_styles(children, map);
+            }
+            FormatElement::Conditional(children) => map_element_styles(children, map),
+        }
+    }
+}
+
+/// Render a list of elements into `output`, returning whether any variable in
+/// it had a value.
+fn render_elements<F>(
+    elements: &[FormatElement],
+    style: Option<Style>,
+    get_variable: &F,
+    output: &mut Vec<Piece>,
+) -> bool
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut has_value = false;
+
+    for element in elements {
+        match element {
+            FormatElement::Text(text) => {
+                output.push(Piece {
+                    variable: None,
+                    text: text.clone(),
+                    style,
+                });
+            }
+            FormatElement::Variable(name) => {
+                if let Some(value) = get_variable(name).filter(|value| !value.is_empty()) {
+                    output.push(Piece {
+                        variable: Some(name.clone()),
+                        text: value,
+                        style,
+                    });
+                    has_value = true;
+                }
+            }
+            FormatElement::Styled(children, group_style) => {
+                has_value |= render_elements(children, Some(*group_style), get_variable, output);
+            }
+            FormatElement::Conditional(children) => {
+                let mut group_output = Vec::new();
+                if render_elements(children, style, get_variable, &mut group_output) {
+                    output.append(&mut group_output);
+                    has_value = true;
+                }
+            }
+        }
+    }
+
+    has_value
+}
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    palette: &'a Palette,
+}
+
+impl Parser<'_> {
+    /// The 1-based column of the current position.
+    fn column(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn error<T>(&self, column: usize, message: impl Into<String>) -> Result<T, FormatError> {
+        Err(FormatError {
+            column,
+            message: message.into(),
+        })
+    }
+
+    /// Parse elements until `closing` is consumed, or until the end of the
+    /// format if `closing` is `None`.
+    fn parse_elements(&mut self, closing: Option<char>) -> Result<Vec<FormatElement>, FormatError> {
+        let mut elements = Vec::new();
+
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => match closing {
+                    Some(closing) => {
+                        return self.error(self.column(), format!("expected '{}'", closing))
+                    }
+                    None => return Ok(elements),
+                },
+            };
+
+            if Some(c) == closing {
+                self.pos += 1;
+                return Ok(elements);
+            }
+
+            match c {
+                '$' => elements.push(self.parse_variable()?),
+                '[' => elements.push(self.parse_styled()?),
+                '(' => {
+                    self.pos += 1;
+                    let children = self.parse_elements(Some(')'))?;
+                    elements.push(FormatElement::Conditional(children));
+                }
+                ']' | ')' => return self.error(self.column(), format!("unmatched '{}'", c)),
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(escaped) => push_text(&mut elements, escaped),
+                        None => {
+                            return self.error(self.column(), "expected a character after '\\'")
+                        }
+                    }
+                    self.pos += 1;
+                }
+                _ => {
+                    push_text(&mut elements, c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn parse_variable(&mut self) -> Result<FormatElement, FormatError> {
+        let column = self.column();
+        self.pos += 1;
+
+        let mut name = String::new();
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            name.push(c);
+            self.pos += 1;
+        }
+
+        if name.is_empty() {
+            return self.error(column, "expected a variable name after '$'");
+        }
+
+        Ok(FormatElement::Variable(name))
+    }
+
+    fn parse_styled(&mut self) -> Result<FormatElement, FormatError> {
+        self.pos += 1;
+        let children = self.parse_elements(Some(']'))?;
+
+        if self.peek() != Some('(') {
+            return self.error(self.column(), "expected '(' and a style after ']'");
+        }
+        self.pos += 1;
+
+        let style_column = self.column();
+        let mut style_string = String::new();
+        loop {
+            match self.peek() {
+                Some(')') => break,
+                Some(c) => style_string.push(c),
+                None => return self.error(self.column(), "expected ')'"),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+
+        match parse_style_string(&style_string, self.palette) {
+            Ok(style) => Ok(FormatElement::Styled(children, style)),
+            Err(err) => self.error(style_column, err.to_string()),
+        }
+    }
+}
+
+/// Append a character to the trailing text element, creating it if needed.
+fn push_text(elements: &mut Vec<FormatElement>, c: char) {
+    match elements.last_mut() {
+        Some(FormatElement::Text(text)) => text.push(c),
+        _ => elements.push(FormatElement::Text(c.to_string())),
+    }
+}
+
+/// The error returned when a format string can't be parsed.
+#[derive(Debug, PartialEq)]
+pub struct FormatError {
+    /// The 1-based column, in characters, at which the error was found.
+    pub column: usize,
+
+    /// A description of the error.
+    pub message: String,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ansi_term::Color;
+
+    fn parse(format: &str) -> Result<PromptFormat, FormatError> {
+        PromptFormat::parse(format, &Palette::default())
+    }
+
+    fn text(value: &str) -> FormatElement {
+        FormatElement::Text(value.to_string())
+    }
+
+    fn variable(name: &str) -> FormatElement {
+        FormatElement::Variable(name.to_string())
+    }
+
+    fn render(format: &str) -> String {
+        parse(format).unwrap().render(|name| match name {
+            "full" => Some(format!("<{}>", name)),
+            "empty" => Some(String::new()),
+            _ => None,
+        })
+    }
+
+    fn error_column(format: &str) -> usize {
+        parse(format).unwrap_err().column
+    }
+
+    #[test]
+    fn parse_text_and_variables() {
+        let format = parse("$directory on $git_branch\n$prompt").unwrap();
+        assert_eq!(
+            format.elements,
+            vec![
+                variable("directory"),
+                text(" on "),
+                variable("git_branch"),
+                text("\n"),
+                variable("prompt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_groups() {
+        let format = parse("([took](bold yellow) $cmd_duration)").unwrap();
+        assert_eq!(
+            format.elements,
+            vec![FormatElement::Conditional(vec![
+                FormatElement::Styled(vec![text("took")], Color::Yellow.bold()),
+                text(" "),
+                variable("cmd_duration"),
+            ])]
+        );
+    }
+
+    #[test]
+    fn parse_escapes() {
+        let format = parse(r"\$\[\(\\").unwrap();
+        assert_eq!(format.elements, vec![text(r"$[(\")]);
+    }
+
+    #[test]
+    fn parse_errors_report_column() {
+        assert_eq!(error_column("$directory $"), 12);
+        assert_eq!(error_column("$rust]"), 6);
+        assert_eq!(error_column("ab)"), 3);
+        assert_eq!(error_column("(abc"), 5);
+        assert_eq!(error_column("[abc]"), 6);
+        assert_eq!(error_column("[abc](bold"), 11);
+        assert_eq!(error_column("[abc](bold mauve)"), 7);
+        assert_eq!(error_column("abc\\"), 5);
+    }
+
+    #[test]
+    fn variables_in_order() {
+        let format = parse("$b [$a](bold) ($c $b)\n$d").unwrap();
+        assert_eq!(format.variables(), vec!["b", "a", "c", "d"]);
+    }
+
+    #[test]
+    fn render_variables() {
+        assert_eq!(render("a $full b $empty c $none"), "a <full> b  c ");
+    }
+
+    #[test]
+    fn render_conditional_groups() {
+        assert_eq!(render("($empty $none)x"), "x");
+        assert_eq!(render("($empty $full)x"), " <full>x");
+        assert_eq!(render("(no variables)x"), "x");
+        assert_eq!(render("(a ($empty) $full)"), "a  <full>");
+    }
+
+    #[test]
+    fn render_pieces_keeps_variables_apart() {
+        let format = parse("a $full ($empty)$none").unwrap();
+        let pieces = format.render_pieces(|name| match name {
+            "full" => Some("<full>".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(
+            pieces,
+            vec![
+                Piece {
+                    variable: None,
+                    text: "a ".to_string(),
+                    style: None,
+                },
+                Piece {
+                    variable: Some("full".to_string()),
+                    text: "<full>".to_string(),
+                    style: None,
+                },
+                Piece {
+                    variable: None,
+                    text: " ".to_string(),
+                    style: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn render_styled_text() {
+        assert_eq!(render("[$full!](red)"), "<full>\u{1b}[31m!\u{1b}[0m");
+    }
+}
@@ -18,32 +18,61 @@ has been developed as a compatibility measure with `eval $(starship init X)`
 fn path_to_starship() -> io::Result<String> {
     let current_exe = env::current_exe()?
         .to_str()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "can't convert to str"))?
+        .ok_or_else(|| io::Error::other("can't convert to str"))?
         .to_string();
     Ok(current_exe)
 }
 
+fn unsupported_shell(shell: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("starship can't be initialized for {:?}", shell),
+    )
+}
+
 /* This prints the setup stub, the short piece of code which sets up the main
 init code. The stub produces the main init script, then evaluates it with
 `source` and process substitution */
-pub fn init_stub() -> io::Result<()> {
+pub fn init_stub(shell: &str) -> io::Result<()> {
     let starship = path_to_starship()?.replace("\"", "\"'\"'\"");
-    print!("source <(\"{}\" init --print-full-init)", starship);
+    match shell {
+        "zsh" => print!("source <(\"{}\" init zsh --print-full-init)", starship),
+        /* Bash 3.2, which macOS still ships, can't `source` a process
+        substitution, so the script is read from a here-string instead */
+        "bash" => print!(
+            "source /dev/stdin <<<\"$(\"{}\" init bash --print-full-init)\"",
+            starship
+        ),
+        /* Fish has no process substitution, but can `source` a pipe */
+        "fish" => print!("\"{}\" init fish --print-full-init | source", starship),
+        _ => return Err(unsupported_shell(shell)),
+    }
 
     Ok(())
 }
 
 /* This function (called when `--print-full-init` is passed to `starship init`)
 prints out the main initialization script */
-pub fn init_main() -> io::Result<()> {
+pub fn init_main(shell: &str) -> io::Result<()> {
     let starship_path = path_to_starship()?.replace("\"", "\"'\"'\"");
 
+    let init_script = match shell {
+        "zsh" => ZSH_INIT,
+        "bash" => BASH_INIT,
+        "fish" => FISH_INIT,
+        _ => return Err(unsupported_shell(shell)),
+    };
+
     // Set up quoting for starship path in case it has spaces.
     let starship_path_string = format!("\"{}\"", starship_path);
-    let script = ZSH_INIT.replace("::STARSHIP::", &starship_path_string);
+    let script = init_script.replace("::STARSHIP::", &starship_path_string);
     print!("{}", script);
 
     Ok(())
 }
 
 const ZSH_INIT: &str = include_str!("starship.zsh");
+
+const BASH_INIT: &str = include_str!("starship.bash");
+
+const FISH_INIT: &str = include_str!("starship.fish");
@@ -0,0 +1,60 @@
+# Bash has no preexec hook, so we use a DEBUG trap to record when a command
+# starts. The trap fires before every simple command, including the ones run
+# from PROMPT_COMMAND, so STARSHIP_PREEXEC_READY makes sure that only the first
+# command after a prompt is drawn records the start time.
+
+# Will be run before the first command of every command line
+starship_preexec() {
+    if [[ "$STARSHIP_PREEXEC_READY" == "true" ]]; then
+        STARSHIP_PREEXEC_READY=false
+        STARSHIP_START_TIME=$SECONDS
+    fi
+}
+
+# Will be run before every prompt draw
+starship_precmd() {
+    # The status was saved at the start of PROMPT_COMMAND, before any other
+    # command there could change $?
+    STATUS=$STARSHIP_STATUS
+
+    # Count the lines of `jobs -p` to get the number of jobs
+    NUM_JOBS=$(jobs -p | wc -l)
+
+    # Compute cmd_duration, if we have a time to consume
+    if [[ -n "${STARSHIP_START_TIME+1}" ]]; then
+        STARSHIP_DURATION=$((SECONDS - STARSHIP_START_TIME))
+        PS1="$(::STARSHIP:: prompt --status=$STATUS --cmd-duration=$STARSHIP_DURATION --jobs="$NUM_JOBS" --terminal-width="$COLUMNS")"
+        unset STARSHIP_START_TIME
+    else
+        PS1="$(::STARSHIP:: prompt --status=$STATUS --jobs="$NUM_JOBS" --terminal-width="$COLUMNS")"
+    fi
+
+    STARSHIP_PREEXEC_READY=true
+}
+
+# If starship precmd/preexec functions are already hooked, don't double-hook them
+# to avoid unnecessary performance degradation in nested shells
+if [[ "$PROMPT_COMMAND" != *"starship_precmd"* ]]; then
+    # Save the status first and draw the prompt last, so that commands already in
+    # PROMPT_COMMAND neither change the status nor look like the next command
+    PROMPT_COMMAND="STARSHIP_STATUS=\$?; ${PROMPT_COMMAND:+$PROMPT_COMMAND; }starship_precmd"
+fi
+
+# Keep any DEBUG trap that is already set, running it before ours
+STARSHIP_DEBUG_TRAP="$(trap -p DEBUG)"
+STARSHIP_DEBUG_TRAP="${STARSHIP_DEBUG_TRAP#trap -- \'}"
+STARSHIP_DEBUG_TRAP="${STARSHIP_DEBUG_TRAP%\' DEBUG}"
+# `trap -p` quotes the body, writing each ' in it as '\''
+STARSHIP_DEBUG_TRAP="${STARSHIP_DEBUG_TRAP//\'\\\'\'/\'}"
+if [[ -z "$STARSHIP_DEBUG_TRAP" ]]; then
+    trap starship_preexec DEBUG
+elif [[ "$STARSHIP_DEBUG_TRAP" != "starship_preexec" && "$STARSHIP_DEBUG_TRAP" != "starship_preexec_all" ]]; then
+    starship_preexec_all() {
+        eval "$STARSHIP_DEBUG_TRAP"
+        starship_preexec
+    }
+    trap starship_preexec_all DEBUG
+fi
+
+STARSHIP_START_TIME=$SECONDS
+export STARSHIP_SHELL="bash"
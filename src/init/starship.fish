@@ -0,0 +1,15 @@
+# Fish draws the prompt with the output of `fish_prompt`, and measures the
+# duration of the last command itself, in milliseconds, as $CMD_DURATION.
+
+function fish_prompt
+    # Save the status, because the commands below will change it
+    set -l exit_code $status
+
+    # Round the duration down to the seconds that cmd_duration expects
+    set -l starship_duration (math --scale=0 "$CMD_DURATION / 1000")
+    set -l num_jobs (count (jobs -p))
+
+    ::STARSHIP:: prompt --status=$exit_code --cmd-duration=$starship_duration --jobs=$num_jobs --terminal-width=$COLUMNS
+end
+
+set -gx STARSHIP_SHELL "fish"
@@ -0,0 +1,435 @@
+use ansi_term::{Color, Style};
+use std::collections::{HashMap, HashSet};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::Config;
+use crate::formatter::{Piece, PromptFormat};
+use crate::module::Module;
+use crate::shell;
+
+/// The number of terminal columns `text` takes up, ignoring escape sequences
+/// and counting wide characters, such as most emoji, as two columns.
+pub fn display_width(text: &str) -> usize {
+    shell::strip_escape_sequences(text).width()
+}
+
+/// A line of rendered output.
+#[derive(Debug, Default, PartialEq)]
+struct Line {
+    width: usize,
+
+    /// The modules whose output starts on this line.
+    modules: Vec<String>,
+}
+
+/// Split rendered pieces into the lines they appear on.
+fn split_lines(pieces: &[Piece]) -> Vec<Line> {
+    let mut lines = vec![Line::default()];
+
+    for piece in pieces {
+        if let Some(name) = &piece.variable {
+            lines.last_mut().unwrap().modules.push(name.clone());
+        }
+
+        let mut parts = piece.text.split('\n');
+        if let Some(first) = parts.next() {
+            lines.last_mut().unwrap().width += display_width(first);
+        }
+        for part in parts {
+            lines.push(Line {
+                width: display_width(part),
+                modules: Vec::new(),
+            });
+        }
+    }
+
+    lines
+}
+
+/// Lay out the rendered modules: draw them as powerline segments if the config
+/// asks for it, then fit them to the terminal if its width is known.
+pub fn lay_out(
+    format: &PromptFormat,
+    modules: &mut HashMap<&str, Module>,
+    config: &Config,
+    terminal_width: Option<usize>,
+) {
+    if config.powerline {
+        apply_powerline(format, modules, &config.powerline_separator);
+    }
+
+    if let Some(width) = terminal_width {
+        fit_to_width(format, modules, config, width);
+        // The separators of modules next to dropped ones point at the wrong
+        // background
+        if config.powerline {
+            apply_powerline(format, modules, &config.powerline_separator);
+        }
+        expand_fill(format, modules, width);
+    }
+}
+
+/// Draw each shown module as a powerline segment, on its own background and
+/// followed by `separator` in that background color, on the background of the
+/// next module on the same line.
+///
+/// Modules without a background color are drawn inverted, so that their color
+/// becomes their background. Modules that start a new line, such as
+/// `line_break`, are left as they are and end the segments of their line.
+pub fn apply_powerline(
+    format: &PromptFormat,
+    modules: &mut HashMap<&str, Module>,
+    separator: &str,
+) {
+    let pieces = format.render_pieces(|name| modules.get(name).map(Module::to_string));
+
+    for line in split_lines(&pieces) {
+        let segments: Vec<&str> = line
+            .modules
+            .iter()
+            .map(String::as_str)
+            .filter(|name| {
+                modules
+                    .get(*name)
+                    .is_some_and(|module| !module.get_value().contains('\n'))
+            })
+            .collect();
+        let backgrounds: Vec<Option<Color>> = segments
+            .iter()
+            .map(|name| powerline_background(modules[*name].get_style()))
+            .collect();
+
+        for (i, name) in segments.iter().enumerate() {
+            let module = match modules.get_mut(*name) {
+                Some(module) => module,
+                None => continue,
+            };
+            let style = module.get_style();
+            let style = if style.background.is_some() {
+                style
+            } else {
+                style.reverse()
+            };
+
+            module.set_style(style);
+            // Segments with their own style would otherwise break the bar
+            if let Some(background) = backgrounds[i] {
+                module.set_segment_background(background);
+            }
+            module.get_prefix().set_value(" ").set_style(style);
+            module.get_suffix().set_value(" ").set_style(style);
+            module
+                .get_separator()
+                .set_value(separator)
+                .set_style(Style {
+                    foreground: backgrounds[i],
+                    background: backgrounds.get(i + 1).copied().flatten(),
+                    ..Style::default()
+                });
+        }
+    }
+}
+
+/// The color a module is drawn on in powerline mode.
+fn powerline_background(style: Style) -> Option<Color> {
+    style.background.or(style.foreground)
+}
+
+/// Drop and shorten modules until every line of the prompt fits in `width`
+/// columns, following the `drop_order` and `shorten_order` of the config.
+///
+/// Only modules on a line that is too wide are changed. If a line still doesn't
+/// fit once every module in both lists is handled, it is left as it is.
+pub fn fit_to_width(
+    format: &PromptFormat,
+    modules: &mut HashMap<&str, Module>,
+    config: &Config,
+    width: usize,
+) {
+    let mut shortened = HashSet::new();
+
+    loop {
+        let pieces = format.render_pieces(|name| modules.get(name).map(Module::to_string));
+        let lines = split_lines(&pieces);
+        let overflowing = lines.iter().find(|line| line.width > width);
+        let line = match overflowing {
+            Some(line) => line,
+            None => return,
+        };
+        let on_line = |name: &&String| line.modules.contains(name);
+
+        if let Some(name) = config.drop_order.iter().find(on_line) {
+            log::debug!("Dropping {} to fit the prompt in {} columns", name, width);
+            modules.remove(name.as_str());
+            continue;
+        }
+
+        let excess = line.width - width;
+        let shortenable = config
+            .shorten_order
+            .iter()
+            .filter(on_line)
+            .find(|name| !shortened.contains(*name));
+        match shortenable {
+            Some(name) => {
+                shortened.insert(name.clone());
+                if let Some(module) = modules.get_mut(name.as_str()) {
+                    let module_width = module.get_value().width();
+                    log::debug!("Shortening {} to fit the prompt in {} columns", name, width);
+                    module.truncate_start(module_width.saturating_sub(excess));
+                }
+            }
+            None => {
+                log::debug!("The prompt doesn't fit in {} columns", width);
+                return;
+            }
+        }
+    }
+}
+
+/// Widen the `fill` module so that the line it is on takes up exactly `width`
+/// columns, pushing the modules after it to the right edge.
+///
+/// The fill is shared by every line it appears on, so only the first of them is
+/// filled. When `$fill` appears more than once on that line, the space is split
+/// evenly between them. A line that is already too wide keeps a single symbol.
+pub fn expand_fill(format: &PromptFormat, modules: &mut HashMap<&str, Module>, width: usize) {
+    let (fill_width, symbol_width) = match modules.get("fill") {
+        Some(fill) => (display_width(&fill.to_string()), fill.get_value().width()),
+        None => return,
+    };
+    // The width of its prefix, suffix and separator, such as in powerline mode
+    let affix_width = fill_width - symbol_width;
+
+    let pieces = format.render_pieces(|name| modules.get(name).map(Module::to_string));
+    let lines = split_lines(&pieces);
+    let line = match lines
+        .iter()
+        .find(|line| line.modules.iter().any(|name| name == "fill"))
+    {
+        Some(line) => line,
+        None => return,
+    };
+
+    let fills = line.modules.iter().filter(|name| *name == "fill").count();
+    let rest = line.width - fills * fill_width;
+    if rest + fills * (affix_width + 1) > width {
+        return;
+    }
+
+    if let Some(fill) = modules.get_mut("fill") {
+        fill.repeat_to_width((width - rest) / fills - affix_width);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::FormatError;
+    use crate::theme::Palette;
+    use ansi_term::Color;
+
+    fn parse(format: &str) -> Result<PromptFormat, FormatError> {
+        PromptFormat::parse(format, &Palette::default())
+    }
+
+    fn module(value: &str) -> Module {
+        let mut module = Module::new();
+        module.set_style(Color::Red.bold());
+        module.append_segment_str(value);
+        module
+    }
+
+    fn config(drop_order: &[&str], shorten_order: &[&str]) -> Config {
+        let mut config = Config::default();
+        config.drop_order = drop_order.iter().map(|name| name.to_string()).collect();
+        config.shorten_order = shorten_order.iter().map(|name| name.to_string()).collect();
+        config
+    }
+
+    fn render(format: &PromptFormat, modules: &HashMap<&str, Module>) -> String {
+        let prompt = format.render(|name| modules.get(name).map(Module::to_string));
+        shell::strip_escape_sequences(&prompt)
+    }
+
+    #[test]
+    fn width_ignores_escapes_and_counts_wide_characters() {
+        assert_eq!(display_width(&Color::Red.paint("abc").to_string()), 3);
+        assert_eq!(display_width("🚀 日本"), 7);
+        assert_eq!(display_width("\u{1b}]8;;https://example.com\u{7}link"), 4);
+    }
+
+    #[test]
+    fn lines_track_their_modules() {
+        let format = parse("$a $b\n$c").unwrap();
+        let pieces = format.render_pieces(|name| Some(format!("<{}>", name)));
+
+        assert_eq!(
+            split_lines(&pieces),
+            vec![
+                Line {
+                    width: 7,
+                    modules: vec!["a".to_string(), "b".to_string()],
+                },
+                Line {
+                    width: 3,
+                    modules: vec!["c".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn drop_modules_in_order() {
+        let format = parse("$a$b$c").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("aaaa"));
+        modules.insert("b", module("bbbb"));
+        modules.insert("c", module("cccc"));
+
+        fit_to_width(&format, &mut modules, &config(&["c", "b"], &[]), 10);
+        assert_eq!(render(&format, &modules), "aaaa bbbb ");
+    }
+
+    #[test]
+    fn only_overflowing_lines_lose_modules() {
+        let format = parse("$a$b\n$c").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("aaaa"));
+        modules.insert("b", module("bbbbbbbbbb"));
+        modules.insert("c", module("cc"));
+
+        fit_to_width(&format, &mut modules, &config(&["c", "b"], &[]), 10);
+        assert_eq!(render(&format, &modules), "aaaa \ncc ");
+    }
+
+    #[test]
+    fn shorten_when_nothing_is_left_to_drop() {
+        let format = parse("$directory$rust").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("directory", module("~/a/very/deep/path"));
+        modules.insert("rust", module("rs"));
+
+        fit_to_width(
+            &format,
+            &mut modules,
+            &config(&["rust"], &["directory"]),
+            12,
+        );
+        assert_eq!(render(&format, &modules), "…/deep/path ");
+    }
+
+    fn fill(symbol: &str) -> Module {
+        let mut fill = module(symbol);
+        fill.get_suffix().set_value("");
+        fill
+    }
+
+    #[test]
+    fn fill_pushes_modules_to_the_right_edge() {
+        let format = parse("$a$fill$b\n$c").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("aa"));
+        modules.insert("fill", fill("."));
+        modules.insert("b", module("日本"));
+        modules.insert("c", module("cccccccccccccccc"));
+
+        expand_fill(&format, &mut modules, 12);
+        assert_eq!(render(&format, &modules), "aa ....日本 \ncccccccccccccccc ");
+    }
+
+    #[test]
+    fn fill_splits_space_and_pads_wide_symbols() {
+        let format = parse("$a$fill$b$fill").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("a"));
+        modules.insert("fill", fill("-="));
+        modules.insert("b", module("b"));
+
+        expand_fill(&format, &mut modules, 14);
+        assert_eq!(render(&format, &modules), "a -=-= b -=-= ");
+
+        expand_fill(&format, &mut modules, 4);
+        assert_eq!(render(&format, &modules), "a -=-= b -=-= ");
+
+        // Each 日 takes two columns, so an odd width leaves a space
+        modules.insert("fill", fill("日"));
+        expand_fill(&format, &mut modules, 14);
+        assert_eq!(render(&format, &modules), "a 日日 b 日日 ");
+    }
+
+    #[test]
+    fn powerline_joins_shown_modules_on_each_line() {
+        let format = parse("$a$missing$b$line_break$c").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("a"));
+        let mut b = module("b");
+        b.set_style(Color::Black.on(Color::Blue));
+        modules.insert("b", b);
+        modules.insert("line_break", fill("\n"));
+        modules.insert("c", module("c"));
+
+        apply_powerline(&format, &mut modules, ">");
+
+        let json = |name: &str| modules[name].to_json(name);
+        assert_eq!(json("a")["style"], "bold inverted fg:red");
+        assert_eq!(json("a")["prefix"]["value"], " ");
+        assert_eq!(json("a")["separator"]["value"], ">");
+        assert_eq!(json("a")["separator"]["style"], "fg:red bg:blue");
+        assert_eq!(json("b")["style"], "fg:black bg:blue");
+        assert_eq!(json("b")["separator"]["style"], "fg:blue");
+        assert_eq!(json("line_break")["separator"]["value"], "");
+        assert_eq!(json("c")["separator"]["style"], "fg:red");
+
+        assert_eq!(render(&format, &modules), " a > b >\n c >");
+    }
+
+    #[test]
+    fn powerline_draws_styled_segments_on_the_module_background() {
+        let format = parse("$a$b").unwrap();
+        let mut modules = HashMap::new();
+        let mut a = module("a");
+        a.append_segment_styled("!", Color::Green);
+        modules.insert("a", a);
+        let mut b = module("b");
+        b.set_style(Color::Black.on(Color::Blue));
+        b.append_segment_styled("?", Color::Yellow.on(Color::Black));
+        modules.insert("b", b);
+
+        apply_powerline(&format, &mut modules, ">");
+        // Applying it again, as `lay_out` does, changes nothing
+        apply_powerline(&format, &mut modules, ">");
+
+        let json = |name: &str| modules[name].to_json(name);
+        assert_eq!(json("a")["segments"][1]["style"], "fg:green bg:red");
+        assert_eq!(json("b")["segments"][0]["style"], "fg:black bg:blue");
+        assert_eq!(json("b")["segments"][1]["style"], "fg:yellow bg:black");
+    }
+
+    #[test]
+    fn fill_leaves_room_for_powerline_affixes() {
+        let format = parse("$a$fill$b").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("a"));
+        modules.insert("fill", fill("."));
+        modules.insert("b", module("b"));
+
+        let mut config = Config::default();
+        config.powerline = true;
+        config.powerline_separator = ">".to_string();
+        lay_out(&format, &mut modules, &config, Some(20));
+
+        assert_eq!(render(&format, &modules), " a > ......... > b >");
+        assert_eq!(display_width(&render(&format, &modules)), 20);
+    }
+
+    #[test]
+    fn give_up_when_nothing_can_change() {
+        let format = parse("$a").unwrap();
+        let mut modules = HashMap::new();
+        modules.insert("a", module("aaaaaaaaaa"));
+
+        fit_to_width(&format, &mut modules, &config(&[], &[]), 5);
+        assert_eq!(render(&format, &modules), "aaaaaaaaaa ");
+    }
+}
@@ -1,9 +1,19 @@
+mod cache;
+mod color;
+mod config;
 mod context;
+mod explain;
+mod formatter;
 mod init;
+mod layout;
 mod module;
 mod modules;
 mod print;
 mod segment;
+mod shell;
+mod style;
+mod theme;
+mod timings;
 mod utils;
 
 use clap::{App, AppSettings, Arg, SubCommand};
@@ -39,6 +49,69 @@ fn main() {
         .help("The number of currently running jobs")
         .takes_value(true);
 
+    let repeat_arg = Arg::with_name("repeat")
+        .long("repeat")
+        .value_name("N")
+        .help("Render the prompt N times and report the min, median and p95 times")
+        .takes_value(true)
+        .default_value("1")
+        .validator(|value| match value.parse::<usize>() {
+            Ok(0) | Err(_) => Err("must be a positive number".to_string()),
+            Ok(_) => Ok(()),
+        });
+
+    let shell_arg = Arg::with_name("shell")
+        .value_name("SHELL")
+        .help("The name of the currently running shell")
+        .possible_values(&["zsh", "bash", "fish"])
+        .default_value("zsh");
+
+    let prompt_shell_arg = Arg::with_name("shell")
+        .long("shell")
+        .value_name("SHELL")
+        .help("The shell to escape the prompt for, instead of $STARSHIP_SHELL")
+        .possible_values(&["zsh", "bash", "fish", "none"])
+        .takes_value(true);
+
+    let right_arg = Arg::with_name("right")
+        .long("right")
+        .help("Print the right prompt instead of the left one");
+
+    let continuation_arg = Arg::with_name("continuation")
+        .long("continuation")
+        .help("Print the continuation prompt, shown while a command continues over several lines")
+        .conflicts_with("right");
+
+    let transient_arg = Arg::with_name("transient")
+        .long("transient")
+        .help("Print the short prompt that replaces the full one once a command is accepted")
+        .conflicts_with_all(&["right", "continuation"]);
+
+    let fast_arg = Arg::with_name("fast")
+        .long("fast")
+        .help("Show slow modules as placeholders, for shells that render them in the background");
+
+    let output_arg = Arg::with_name("output")
+        .long("output")
+        .value_name("FORMAT")
+        .help("Print the modules as JSON, or as plain text without styles, instead of a prompt")
+        .possible_values(&["json", "plain"])
+        .takes_value(true)
+        .conflicts_with_all(&["continuation", "transient"]);
+
+    let terminal_width_arg = Arg::with_name("terminal_width")
+        .long("terminal-width")
+        .value_name("COLUMNS")
+        .help("The width of the terminal, to drop or shorten modules that don't fit")
+        .takes_value(true);
+
+    let color_arg = Arg::with_name("color")
+        .long("color")
+        .value_name("COLORS")
+        .help("The colors the terminal supports, instead of detecting them from NO_COLOR, COLORTERM and TERM")
+        .possible_values(&["auto", "never", "16", "256", "truecolor"])
+        .takes_value(true);
+
     let init_scripts_arg = Arg::with_name("print_full_init")
         .long("print-full-init")
         .help("Print the main initialization script (as opposed to the init stub)");
@@ -54,27 +127,72 @@ fn main() {
         .subcommand(
             SubCommand::with_name("init")
                 .about("Prints the shell function used to execute starship")
+                .arg(&shell_arg)
                 .arg(&init_scripts_arg),
         )
+        .subcommand(
+            SubCommand::with_name("cache")
+                .about("Manages the cache of tool versions")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(SubCommand::with_name("clear").about("Removes every cached version")),
+        )
         .subcommand(
             SubCommand::with_name("prompt")
                 .about("Prints the full starship prompt")
                 .arg(&status_code_arg)
                 .arg(&path_arg)
                 .arg(&cmd_duration_arg)
+                .arg(&jobs_arg)
+                .arg(&prompt_shell_arg)
+                .arg(&right_arg)
+                .arg(&continuation_arg)
+                .arg(&transient_arg)
+                .arg(&fast_arg)
+                .arg(&output_arg)
+                .arg(&terminal_width_arg)
+                .arg(&color_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("explain")
+                .about("Explains what each module of the prompt shows and why")
+                .arg(&status_code_arg)
+                .arg(&path_arg)
+                .arg(&cmd_duration_arg)
                 .arg(&jobs_arg),
         )
+        .subcommand(
+            SubCommand::with_name("timings")
+                .about("Prints how long each module of the prompt takes to render")
+                .arg(&status_code_arg)
+                .arg(&path_arg)
+                .arg(&cmd_duration_arg)
+                .arg(&jobs_arg)
+                .arg(&repeat_arg),
+        )
         .get_matches();
 
     match matches.subcommand() {
         ("init", Some(sub_m)) => {
+            // Defaults to zsh, which was the only supported shell at first
+            let shell_name = sub_m.value_of("shell").unwrap();
             if sub_m.is_present("print_full_init") {
-                init::init_main().expect("can't init_main");
+                init::init_main(shell_name).expect("can't init_main");
             } else {
-                init::init_stub().expect("can't init_stub");
+                init::init_stub(shell_name).expect("can't init_stub");
+            }
+        }
+        ("cache", Some(sub_m)) => {
+            if let ("clear", Some(_)) = sub_m.subcommand() {
+                cache::clear().expect("can't clear cache");
             }
         }
         ("prompt", Some(sub_m)) => print::prompt(sub_m.clone()),
+        ("explain", Some(sub_m)) => explain::explain(sub_m.clone()),
+        ("timings", Some(sub_m)) => {
+            // Checked by the validator of `repeat_arg`
+            let repeat = sub_m.value_of("repeat").unwrap().parse().unwrap();
+            timings::timings(sub_m.clone(), repeat);
+        }
         _ => {}
     }
 }
@@ -1,7 +1,38 @@
+use crate::color::ColorSupport;
 use crate::segment::Segment;
-use ansi_term::Style;
+use crate::style::style_to_string;
 use ansi_term::{ANSIString, ANSIStrings};
+use ansi_term::{Color, Style};
+use serde_json::json;
 use std::fmt;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// List of all modules
+pub const ALL_MODULES: &[&str] = &[
+    "aws",
+    "cmd_duration",
+    "directory",
+    "env_var",
+    "fill",
+    "git_branch",
+    "git_state",
+    "git_status",
+    "git_track",
+    "golang",
+    "jobs",
+    "kube",
+    "line_break",
+    "plaio",
+    "prompt",
+    "python",
+    "rust",
+    "tailscale",
+];
+
+/// Modules that can take long enough to hold up the prompt in large repositories,
+/// as they scan the working tree or walk the commit history. Shells that render
+/// the prompt in the background first show them as placeholders.
+pub const SLOW_MODULES: &[&str] = &["git_status", "git_track"];
 
 /// A module is a collection of segments showing data for a single integration
 /// (e.g. The git module shows the current git branch and status)
@@ -17,9 +48,22 @@ pub struct Module {
 
     /// The suffix used to separate the current module from the next one.
     suffix: Affix,
+
+    /// Drawn after the suffix to join the module to the next one, in powerline
+    /// mode. Empty otherwise.
+    separator: Affix,
+
+    /// What the module's output means, for `starship explain`.
+    description: String,
+
+    /// Why the module is shown, for `starship explain`.
+    reason: String,
+
+    /// The colors the terminal can show, which the styles are converted to.
+    colors: ColorSupport,
 }
 
-impl<'a> Module {
+impl Module {
     /// Creates a module with no segments.
     pub fn new() -> Module {
         Module {
@@ -27,6 +71,10 @@ impl<'a> Module {
             prefix: Affix::default_prefix(),
             segments: Vec::new(),
             suffix: Affix::default_suffix(),
+            separator: Affix::empty(),
+            description: String::new(),
+            reason: String::new(),
+            colors: ColorSupport::TrueColor,
         }
     }
 
@@ -36,11 +84,83 @@ impl<'a> Module {
         self.segments.push(segment);
     }
 
+    /// Appends a segment drawn with `style` instead of the style of the module,
+    /// such as to color parts of the module differently.
+    pub fn append_segment_styled<T>(&mut self, value: &str, style: T)
+    where
+        T: Into<Style>,
+    {
+        let mut segment = Segment::new();
+        segment.set_value(value).set_style(style);
+        self.segments.push(segment);
+    }
+
     /// Whether a module has non-empty segments
     pub fn is_empty(&self) -> bool {
         self.segments.is_empty()
     }
 
+    /// Shortens the segments to at most `max_width` columns, replacing their
+    /// start with `…`. Returns whether anything was removed.
+    pub fn truncate_start(&mut self, max_width: usize) -> bool {
+        const ELLIPSIS: &str = "…";
+
+        let width = self.get_value().width();
+        let max_width = max_width.max(ELLIPSIS.width());
+        if width <= max_width {
+            return false;
+        }
+
+        let mut excess = width - max_width + ELLIPSIS.width();
+        for segment in &mut self.segments {
+            let mut chars = segment.get_value().chars();
+            while excess > 0 {
+                match chars.next() {
+                    Some(c) => excess = excess.saturating_sub(c.width().unwrap_or(0)),
+                    None => break,
+                }
+            }
+            let rest = chars.as_str().to_string();
+            segment.set_value(rest);
+        }
+
+        self.segments
+            .retain(|segment| !segment.get_value().is_empty());
+        let mut ellipsis = Segment::new();
+        ellipsis.set_value(ELLIPSIS);
+        self.segments.insert(0, ellipsis);
+        true
+    }
+
+    /// Repeats the segments until they take up `width` columns, padding with
+    /// spaces when the segments don't divide the width evenly.
+    pub fn repeat_to_width(&mut self, width: usize) {
+        let value = self.get_value();
+        let value_width = value.width();
+        if value_width == 0 {
+            return;
+        }
+
+        let mut filled = value.repeat(width / value_width);
+        filled.push_str(&" ".repeat(width % value_width));
+
+        let mut segment = Segment::new();
+        segment.set_value(filled);
+        self.segments = vec![segment];
+    }
+
+    /// Draws the segments that have their own style on `background`, unless
+    /// their style sets a background of its own.
+    pub fn set_segment_background(&mut self, background: Color) {
+        for segment in &mut self.segments {
+            if let Some(style) = segment.get_style() {
+                if style.background.is_none() {
+                    segment.set_style(style.on(background));
+                }
+            }
+        }
+    }
+
     /// Get the module's prefix
     pub fn get_prefix(&mut self) -> &mut Affix {
         &mut self.prefix
@@ -51,6 +171,44 @@ impl<'a> Module {
         &mut self.suffix
     }
 
+    /// Get the separator drawn after the module's suffix
+    pub fn get_separator(&mut self) -> &mut Affix {
+        &mut self.separator
+    }
+
+    /// Describes what the module's output means, for `starship explain`.
+    pub fn set_description<T>(&mut self, description: T) -> &mut Module
+    where
+        T: Into<String>,
+    {
+        self.description = description.into();
+        self
+    }
+
+    /// Get the description of what the module's output means
+    pub fn get_description(&self) -> &str {
+        &self.description
+    }
+
+    /// Describes why the module is shown, for `starship explain`.
+    pub fn set_reason<T>(&mut self, reason: T) -> &mut Module
+    where
+        T: Into<String>,
+    {
+        self.reason = reason.into();
+        self
+    }
+
+    /// Get the reason the module is shown
+    pub fn get_reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// The text of the module's segments, without styling, prefix or suffix
+    pub fn get_value(&self) -> String {
+        self.segments.iter().map(Segment::get_value).collect()
+    }
+
     /// Sets the style of the segment.
     ///
     /// Accepts either `Color` or `Style`.
@@ -62,65 +220,71 @@ impl<'a> Module {
         self
     }
 
+    /// The style inherited by the module's segments
+    pub fn get_style(&self) -> Style {
+        self.style
+    }
+
+    /// Sets the colors the terminal can show. Styles are converted to the
+    /// nearest colors it supports when the module is printed.
+    pub fn set_color_support(&mut self, colors: ColorSupport) -> &mut Module {
+        self.colors = colors;
+        self
+    }
+
     /// Returns a vector of colored ANSIString elements to be later used with
     /// `ANSIStrings()` to optimize ANSI codes
-    pub fn ansi_strings(&self) -> Vec<ANSIString> {
-        let ansi_strings = self
+    pub fn ansi_strings(&self) -> Vec<ANSIString<'_>> {
+        let mut ansi_strings = self
             .segments
             .iter()
-            .map(|x| x.ansi_string(&self.style))
+            .map(|x| x.ansi_string(&self.colors.apply(self.segment_style(x))))
             .collect::<Vec<ANSIString>>();
 
-        let mut ansi_strings = ansi_strings_modified(ansi_strings);
-
-        ansi_strings.insert(0, self.prefix.ansi_string());
-        ansi_strings.push(self.suffix.ansi_string());
+        ansi_strings.insert(0, self.prefix.ansi_string(self.colors));
+        ansi_strings.push(self.suffix.ansi_string(self.colors));
+        ansi_strings.push(self.separator.ansi_string(self.colors));
 
         ansi_strings
     }
+
+    /// Describes the module as JSON, for integrations that want its contents
+    /// rather than the styled prompt
+    pub fn to_json(&self, name: &str) -> serde_json::Value {
+        let segments: Vec<_> = self
+            .segments
+            .iter()
+            .map(|segment| {
+                json!({
+                    "value": segment.get_value(),
+                    "style": style_to_string(&self.segment_style(segment)),
+                })
+            })
+            .collect();
+
+        json!({
+            "name": name,
+            "style": style_to_string(&self.style),
+            "prefix": self.prefix.to_json(),
+            "segments": segments,
+            "suffix": self.suffix.to_json(),
+            "separator": self.separator.to_json(),
+        })
+    }
+
+    /// The style a segment is drawn with: its own, or else the module's.
+    fn segment_style(&self, segment: &Segment) -> Style {
+        segment.get_style().unwrap_or(self.style)
+    }
 }
 
-impl<'a> fmt::Display for Module {
+impl fmt::Display for Module {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let ansi_strings = self.ansi_strings();
         write!(f, "{}", ANSIStrings(&ansi_strings))
     }
 }
 
-/// Many shells cannot deal with raw unprintable characters (like ANSI escape sequences) and
-/// miscompute the cursor position as a result, leading to strange visual bugs. Here, we wrap these
-/// characters in shell-specific escape codes to indicate to the shell that they are zero-length.
-fn ansi_strings_modified(ansi_strings: Vec<ANSIString>) -> Vec<ANSIString> {
-    const ESCAPE_BEGIN: char = '\u{1b}';
-    const MAYBE_ESCAPE_END: char = 'm';
-    ansi_strings
-        .iter()
-        .map(|ansi| {
-            let mut escaped = false;
-            let final_string: String = ansi
-                .to_string()
-                .chars()
-                .map(|x| match x {
-                    ESCAPE_BEGIN => {
-                        escaped = true;
-                        String::from("\u{25}\u{7b}\u{1b}") // => %{ESC
-                    }
-                    MAYBE_ESCAPE_END => {
-                        if escaped {
-                            escaped = false;
-                            String::from("m\u{25}\u{7d}") // => m%}
-                        } else {
-                            x.to_string()
-                        }
-                    }
-                    _ => x.to_string(),
-                })
-                .collect();
-            ANSIString::from(final_string)
-        })
-        .collect::<Vec<ANSIString>>()
-}
-
 /// Module affixes are to be used for the prefix or suffix of a module.
 pub struct Affix {
     /// The affix's style.
@@ -131,6 +295,14 @@ pub struct Affix {
 }
 
 impl Affix {
+    /// An affix that prints nothing.
+    pub fn empty() -> Self {
+        Self {
+            style: Style::default(),
+            value: String::new(),
+        }
+    }
+
     pub fn default_prefix() -> Self {
         Self {
             style: Style::default(),
@@ -165,14 +337,59 @@ impl Affix {
         self
     }
 
-    /// Generates the colored ANSIString output.
-    pub fn ansi_string(&self) -> ANSIString {
-        self.style.paint(&self.value)
+    /// Describes the affix as JSON, for `Module::to_json`
+    fn to_json(&self) -> serde_json::Value {
+        json!({
+            "value": self.value,
+            "style": style_to_string(&self.style),
+        })
+    }
+
+    /// Generates the colored ANSIString output, using the nearest colors to
+    /// its style that the terminal can show.
+    pub fn ansi_string(&self, colors: ColorSupport) -> ANSIString<'_> {
+        colors.apply(self.style).paint(&self.value)
     }
 }
 
 impl fmt::Display for Affix {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.ansi_string())
+        write!(f, "{}", self.ansi_string(ColorSupport::TrueColor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ansi_term::Color;
+
+    #[test]
+    fn styled_segments_override_module_style() {
+        let mut module = Module::new();
+        module.set_style(Color::White);
+        module.append_segment_styled("⇡1", Color::Green);
+        module.append_segment_str(" ");
+        module.append_segment_styled("⇣2", Color::RGB(255, 0, 0));
+
+        let strings = [
+            Color::Green.paint("⇡1"),
+            Color::White.paint(" "),
+            Color::RGB(255, 0, 0).paint("⇣2"),
+            Style::default().paint(" "),
+        ];
+        assert_eq!(module.to_string(), ANSIStrings(&strings).to_string());
+
+        let json = module.to_json("git_track");
+        assert_eq!(json["segments"][0]["style"], "fg:green");
+        assert_eq!(json["segments"][1]["style"], "fg:white");
+    }
+
+    #[test]
+    fn styled_segments_follow_color_support() {
+        let mut module = Module::new();
+        module.set_color_support(ColorSupport::Basic);
+        module.append_segment_styled("!", Color::RGB(200, 0, 0));
+
+        assert_eq!(module.to_string(), format!("{} ", Color::Red.paint("!")));
     }
 }
@@ -1,12 +1,37 @@
-use ansi_term::Color;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[cmd_duration]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct CmdDurationConfig {
+    /// The shortest duration, in seconds, that will be shown
+    min_time: u64,
+    /// Printed before the duration
+    prefix: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for CmdDurationConfig {
+    fn default() -> Self {
+        Self {
+            min_time: 2,
+            prefix: "tók ".to_string(),
+            style: ThemedStyle::new("warning dimmed"),
+        }
+    }
+}
 
 /// Outputs the time it took the last command to execute
 ///
 /// Will only print if last command took more than a certain amount of time to
 /// execute. Default is two seconds, but can be set by config option `min_time`.
 pub fn module(context: &Context) -> Option<Module> {
+    let config: CmdDurationConfig = context.config.get_module_config("cmd_duration");
     let mut module = context.new_module();
 
     let props = &context.properties;
@@ -16,15 +41,17 @@ pub fn module(context: &Context) -> Option<Module> {
         .parse::<u64>()
         .ok()?;
 
-    let config_min = 2;
-
-    let module_color = match elapsed {
-        time if time < config_min => return None,
-        _ => Color::Yellow.dimmed(),
-    };
+    if elapsed < config.min_time {
+        return None;
+    }
 
-    module.set_style(module_color);
-    module.append_segment_str(&format!("tók {}", render_time(elapsed)));
+    module.set_style(config.style.resolve(&context.palette));
+    module.append_segment_str(&format!("{}{}", config.prefix, render_time(elapsed)));
+    module.set_description("How long the last command took");
+    module.set_reason(format!(
+        "the last command took at least {}s",
+        config.min_time
+    ));
 
     Some(module)
 }
@@ -60,18 +87,22 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn test_10s() {
         assert_eq!(render_time(10 as u64), "10s")
     }
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn test_90s() {
         assert_eq!(render_time(90 as u64), "1m30s")
     }
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn test_10110s() {
         assert_eq!(render_time(10110 as u64), "2h48m30s")
     }
     #[test]
+    #[allow(clippy::unnecessary_cast)]
     fn test_1d() {
         assert_eq!(render_time(86400 as u64), "1d")
     }
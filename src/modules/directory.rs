@@ -1,8 +1,29 @@
-use ansi_term::Color;
 use path_slash::PathExt;
+use serde::Deserialize;
 use std::path::Path;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[directory]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct DirectoryConfig {
+    /// The number of path components to keep, `0` disables truncation
+    truncation_length: usize,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for DirectoryConfig {
+    fn default() -> Self {
+        Self {
+            truncation_length: 7,
+            style: ThemedStyle::new("accent bold"),
+        }
+    }
+}
 
 /// Creates a module with the current directory
 ///
@@ -13,13 +34,15 @@ use super::{Context, Module};
 ///     - Paths containing a git repo will contract to begin at the repo root
 ///
 /// **Truncation**
-/// Paths will be limited in length to `3` path components by default.
+/// Paths will be limited in length to `7` path components by default, which can
+/// be changed with the config option `truncation_length`.
 pub fn module(context: &Context) -> Option<Module> {
     const HOME_SYMBOL: &str = "~";
 
+    let config: DirectoryConfig = context.config.get_module_config("directory");
     let mut module = context.new_module();
 
-    module.set_style(Color::Cyan.bold());
+    module.set_style(config.style.resolve(&context.palette));
 
     let current_dir = &context.current_dir;
 
@@ -40,9 +63,14 @@ pub fn module(context: &Context) -> Option<Module> {
     };
 
     // Truncate the dir string to the maximum number of path components
-    let truncated_dir_string = truncate(dir_string, 7);
+    let truncated_dir_string = truncate(dir_string, config.truncation_length);
 
     module.append_segment_str(&truncated_dir_string);
+    module.set_description(format!(
+        "The current directory, {}",
+        current_dir.to_string_lossy()
+    ));
+    module.set_reason("the directory is always shown");
 
     Some(module)
 }
@@ -84,7 +112,7 @@ fn truncate(dir_string: String, length: usize) -> String {
     let mut components = dir_string.split('/').collect::<Vec<&str>>();
 
     // If the first element is "" then there was a leading "/" and we should remove it so we can check the actual count of components
-    if components[0] == "" {
+    if components[0].is_empty() {
         components.remove(0);
     }
 
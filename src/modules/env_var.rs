@@ -0,0 +1,520 @@
+use ansi_term::Style;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+
+use super::{Context, Module};
+use crate::formatter::PromptFormat;
+use crate::style::{deserialize_style, ThemedStyle};
+use crate::theme::Palette;
+
+/// The modules that are defined as `env_var` entries, and the options they
+/// start from. Their tables in the config file override these options.
+const PRESETS: &[(&str, &str)] = &[
+    (
+        "aws",
+        r#"
+        variable = "AWS_PROFILE"
+        symbol = "~@"
+        format = "$symbol$value(@$region)"
+        style = "warning"
+        description = "The active AWS profile, and its region unless it is the default one"
+
+        [variables.region]
+        variable = "AWS_DEFAULT_REGION"
+        fallback_variable = "AWS_REGION"
+        hide = ["eu-central-1"]
+        "#,
+    ),
+    (
+        "kube",
+        r#"
+        variable = "EKS_CLUSTER"
+        show_empty = true
+        symbol = "|->"
+        format = "$symbol$value(/$namespace)"
+        style = "highlight"
+        description = "The active EKS cluster, and KUBE_NS namespace if set"
+
+        [variables.namespace]
+        variable = "KUBE_NS"
+        "#,
+    ),
+    (
+        "plaio",
+        r#"
+        variable = "PLAIO_ENV"
+        show_empty = true
+        symbol = "🅿️ "
+        style = "text"
+        description = "The active Plaio environment"
+        "#,
+    ),
+    (
+        "tailscale",
+        r#"
+        variable = "TS_EXIT_NODE"
+        show_empty = true
+        symbol = "↗tailscale"
+        format = "$symbol"
+        style = "text"
+        description = "Traffic is routed through a Tailscale exit node"
+        "#,
+    ),
+];
+
+/// An environment variable, and how its value is shown.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Variable {
+    /// The name of the environment variable
+    variable: String,
+    /// Read when `variable` is unset, or empty without `show_empty`
+    fallback_variable: Option<String>,
+    /// Whether a variable that is set but empty is shown, rather than treated
+    /// as unset
+    show_empty: bool,
+    /// Values that are shown as a different label
+    labels: HashMap<String, String>,
+    /// Values that hide the variable, where `*` matches any text
+    hide: Vec<String>,
+}
+
+impl Variable {
+    /// The label of the variable's value and the name of the variable that was
+    /// read, unless it is unset, empty or hidden.
+    fn resolve<F>(&self, get_env: &F) -> Option<(String, &str)>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let read = |name: &str| get_env(name).filter(|value| self.show_empty || !value.is_empty());
+        let (value, name) = match read(&self.variable) {
+            Some(value) => (value, self.variable.as_str()),
+            None => {
+                let fallback = self.fallback_variable.as_deref()?;
+                (read(fallback)?, fallback)
+            }
+        };
+
+        if self
+            .hide
+            .iter()
+            .any(|pattern| matches_pattern(pattern, &value))
+        {
+            return None;
+        }
+        let label = self.labels.get(&value).cloned().unwrap_or(value);
+        Some((label, name))
+    }
+}
+
+/// An entry of the `[env_var]` table of the config file, or the table of one of
+/// its presets.
+#[derive(Deserialize)]
+#[serde(default)]
+struct EnvVarEntry {
+    #[serde(flatten)]
+    value: Variable,
+    /// Shown through `$symbol` in the format
+    symbol: String,
+    /// How the entry is shown: `$value` is the value of `variable`, `$symbol` the
+    /// symbol, and any other `$name` is the value of `variables.name`. Groups in
+    /// parentheses are hidden when their variables are empty.
+    format: String,
+    /// The style of the entry
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+    /// Other variables shown in the format
+    variables: HashMap<String, Variable>,
+    /// What the entry means, for `starship explain`
+    description: Option<String>,
+}
+
+impl Default for EnvVarEntry {
+    fn default() -> Self {
+        Self {
+            value: Variable::default(),
+            symbol: String::new(),
+            format: "$symbol$value".to_string(),
+            style: ThemedStyle::new("text"),
+            variables: HashMap::new(),
+            description: None,
+        }
+    }
+}
+
+/// What an entry shows, and why.
+#[derive(Debug, PartialEq)]
+struct Rendered {
+    /// The text of the entry, in runs of the same style. Text outside of
+    /// `[text](style)` groups has no style of its own.
+    segments: Vec<(String, Option<Style>)>,
+    description: String,
+    reason: String,
+}
+
+impl EnvVarEntry {
+    /// Render the entry, unless its variable is unset or hidden.
+    fn render<F>(&self, get_env: &F, palette: &Palette) -> Option<Rendered>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let (value, name) = self.value.resolve(get_env)?;
+
+        let format = match PromptFormat::parse(&self.format, palette) {
+            Ok(format) => format,
+            Err(err) => {
+                log::warn!("Invalid format {:?} for {}: {}", self.format, name, err);
+                return None;
+            }
+        };
+
+        let pieces = format.render_pieces(|variable| match variable {
+            "value" => Some(value.clone()),
+            "symbol" => Some(self.symbol.clone()),
+            _ => {
+                let (value, _) = self.variables.get(variable)?.resolve(get_env)?;
+                Some(value)
+            }
+        });
+        let mut segments: Vec<(String, Option<Style>)> = Vec::new();
+        for piece in pieces.into_iter().filter(|piece| !piece.text.is_empty()) {
+            match segments.last_mut() {
+                Some((text, style)) if *style == piece.style => text.push_str(&piece.text),
+                _ => segments.push((piece.text, piece.style)),
+            }
+        }
+        if segments.is_empty() {
+            return None;
+        }
+
+        Some(Rendered {
+            segments,
+            description: self
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("The value of {}", name)),
+            reason: format!("{} is set", name),
+        })
+    }
+}
+
+/// Creates a module with the environment variables of each entry of the
+/// `[env_var]` table that is set, in order of their names
+pub fn module(context: &Context) -> Option<Module> {
+    let table: toml::value::Table = context.config.get_module_config("env_var");
+    let mut module = context.new_module();
+    let mut descriptions = Vec::new();
+    let mut reasons = Vec::new();
+
+    for (name, entry) in table.into_iter().collect::<BTreeMap<_, _>>() {
+        // Options of the module itself rather than entries
+        if !entry.is_table() {
+            continue;
+        }
+        let entry: EnvVarEntry = match entry.try_into() {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::warn!("Invalid options for env_var entry {:?}: {}", name, err);
+                continue;
+            }
+        };
+
+        if let Some(rendered) = entry.render(&read_env, &context.palette) {
+            if !module.is_empty() {
+                module.append_segment_str(" ");
+            }
+            let entry_style = entry.style.resolve(&context.palette);
+            for (text, style) in &rendered.segments {
+                module.append_segment_styled(text, style.unwrap_or(entry_style));
+            }
+            descriptions.push(rendered.description);
+            reasons.push(rendered.reason);
+        }
+    }
+
+    if module.is_empty() {
+        return None;
+    }
+    module.set_description(descriptions.join("; "));
+    module.set_reason(reasons.join(", "));
+
+    Some(module)
+}
+
+/// Creates a module for one of the presets, such as `aws`, or `None` if there
+/// is no preset with that name
+pub fn preset(name: &str, context: &Context) -> Option<Module> {
+    let (_, defaults) = PRESETS.iter().find(|(preset, _)| *preset == name)?;
+    let entry = preset_entry(name, defaults, context.config.get_module_config(name));
+    let rendered = entry.render(&read_env, &context.palette)?;
+
+    let mut module = context.new_module();
+    module.set_style(entry.style.resolve(&context.palette));
+    for (text, style) in &rendered.segments {
+        match style {
+            Some(style) => module.append_segment_styled(text, *style),
+            None => module.append_segment_str(text),
+        }
+    }
+    module.set_description(rendered.description);
+    module.set_reason(rendered.reason);
+
+    Some(module)
+}
+
+/// The entry of a preset, with the options of `overrides` replacing its own, or
+/// only its own options if the overrides are invalid.
+fn preset_entry(name: &str, defaults: &str, mut overrides: toml::value::Table) -> EnvVarEntry {
+    let defaults: toml::value::Table = toml::from_str(defaults).expect("presets are valid TOML");
+    apply_aliases(name, &mut overrides);
+    let mut table = defaults.clone();
+    merge_tables(&mut table, overrides);
+
+    toml::Value::Table(table).try_into().unwrap_or_else(|err| {
+        log::warn!("Invalid options for module {:?}: {}", name, err);
+        toml::Value::Table(defaults)
+            .try_into()
+            .expect("presets are valid entries")
+    })
+}
+
+/// Replace the options that presets had when they were modules of their own
+/// with the options they are now written as.
+fn apply_aliases(name: &str, overrides: &mut toml::value::Table) {
+    if name != "aws" {
+        return;
+    }
+    // `default_region` was the region left out of the module
+    if let Some(region) = overrides.remove("default_region") {
+        let mut region_table = toml::value::Table::new();
+        region_table.insert("hide".to_string(), toml::Value::Array(vec![region]));
+        let mut variables = toml::value::Table::new();
+        variables.insert("region".to_string(), toml::Value::Table(region_table));
+        let mut alias = toml::value::Table::new();
+        alias.insert("variables".to_string(), toml::Value::Table(variables));
+
+        // Options that are set explicitly win over the alias
+        merge_tables(&mut alias, std::mem::take(overrides));
+        *overrides = alias;
+    }
+}
+
+/// Merge `overrides` into `table`, keeping the options of nested tables that
+/// `overrides` doesn't set.
+fn merge_tables(table: &mut toml::value::Table, overrides: toml::value::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(nested)), toml::Value::Table(value)) => {
+                merge_tables(nested, value)
+            }
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+fn read_env(name: &str) -> Option<String> {
+    env::var(name).ok()
+}
+
+/// Whether `value` matches `pattern`, in which `*` matches any text.
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == value,
+        Some((prefix, rest)) => {
+            let tail = match value.strip_prefix(prefix) {
+                Some(tail) => tail,
+                None => return false,
+            };
+            // Try every position for the `*`, as the rest may contain more of them
+            tail.char_indices()
+                .map(|(i, _)| i)
+                .chain(Some(tail.len()))
+                .any(|i| matches_pattern(rest, &tail[i..]))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ansi_term::Color;
+
+    fn render(entry: &EnvVarEntry, env: &[(&str, &str)]) -> Option<Rendered> {
+        let env: HashMap<_, _> = env.iter().copied().collect();
+        entry.render(
+            &|name: &str| env.get(name).map(|value| value.to_string()),
+            &Palette::default(),
+        )
+    }
+
+    fn text(rendered: Option<Rendered>) -> Option<String> {
+        let segments = rendered?.segments;
+        Some(segments.into_iter().map(|(text, _)| text).collect())
+    }
+
+    fn preset_with(name: &str, overrides: &str) -> EnvVarEntry {
+        let (_, defaults) = PRESETS.iter().find(|(preset, _)| *preset == name).unwrap();
+        preset_entry(name, defaults, toml::from_str(overrides).unwrap())
+    }
+
+    fn render_preset(name: &str, env: &[(&str, &str)]) -> Option<String> {
+        text(render(&preset_with(name, ""), env))
+    }
+
+    #[test]
+    fn presets_keep_their_output() {
+        assert_eq!(render_preset("aws", &[]), None);
+        assert_eq!(
+            render_preset("aws", &[("AWS_PROFILE", "dev")]),
+            Some("~@dev".to_string())
+        );
+        assert_eq!(
+            render_preset(
+                "aws",
+                &[
+                    ("AWS_PROFILE", "dev"),
+                    ("AWS_DEFAULT_REGION", "eu-central-1")
+                ]
+            ),
+            Some("~@dev".to_string())
+        );
+        assert_eq!(
+            render_preset(
+                "aws",
+                &[("AWS_PROFILE", "dev"), ("AWS_REGION", "us-east-1")]
+            ),
+            Some("~@dev@us-east-1".to_string())
+        );
+
+        assert_eq!(
+            render_preset("kube", &[("EKS_CLUSTER", "prod"), ("KUBE_NS", "web")]),
+            Some("|->prod/web".to_string())
+        );
+        assert_eq!(
+            render_preset("kube", &[("EKS_CLUSTER", "prod")]),
+            Some("|->prod".to_string())
+        );
+        assert_eq!(
+            render_preset("plaio", &[("PLAIO_ENV", "staging")]),
+            Some("🅿️ staging".to_string())
+        );
+        assert_eq!(
+            render_preset("tailscale", &[("TS_EXIT_NODE", "nyc")]),
+            Some("↗tailscale".to_string())
+        );
+    }
+
+    #[test]
+    fn presets_show_variables_that_are_set_but_empty() {
+        assert_eq!(
+            render_preset("tailscale", &[("TS_EXIT_NODE", "")]),
+            Some("↗tailscale".to_string())
+        );
+        assert_eq!(
+            render_preset("plaio", &[("PLAIO_ENV", "")]),
+            Some("🅿️ ".to_string())
+        );
+        assert_eq!(
+            render_preset("kube", &[("EKS_CLUSTER", "")]),
+            Some("|->".to_string())
+        );
+        // Only a profile that isn't empty is active
+        assert_eq!(render_preset("aws", &[("AWS_PROFILE", "")]), None);
+    }
+
+    #[test]
+    fn overrides_merge_into_nested_tables() {
+        let entry = preset_with(
+            "aws",
+            r#"
+            symbol = "aws:"
+            [variables.region]
+            hide = []
+            "#,
+        );
+
+        let env = [("AWS_PROFILE", "dev"), ("AWS_REGION", "eu-central-1")];
+        assert_eq!(
+            text(render(&entry, &env)),
+            Some("aws:dev@eu-central-1".to_string())
+        );
+    }
+
+    #[test]
+    fn aws_default_region_hides_the_region() {
+        let entry = preset_with("aws", r#"default_region = "us-east-1""#);
+
+        let env = [("AWS_PROFILE", "dev"), ("AWS_REGION", "us-east-1")];
+        assert_eq!(text(render(&entry, &env)), Some("~@dev".to_string()));
+        let env = [("AWS_PROFILE", "dev"), ("AWS_REGION", "eu-central-1")];
+        assert_eq!(
+            text(render(&entry, &env)),
+            Some("~@dev@eu-central-1".to_string())
+        );
+
+        // An explicit `hide` wins over the alias
+        let entry = preset_with(
+            "aws",
+            r#"
+            default_region = "us-east-1"
+            [variables.region]
+            hide = []
+            "#,
+        );
+        let env = [("AWS_PROFILE", "dev"), ("AWS_REGION", "us-east-1")];
+        assert_eq!(
+            text(render(&entry, &env)),
+            Some("~@dev@us-east-1".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_overrides_fall_back_to_the_preset() {
+        let entry = preset_with("kube", r#"symbol = ["not", "a", "string"]"#);
+
+        let env = [("EKS_CLUSTER", "prod")];
+        assert_eq!(text(render(&entry, &env)), Some("|->prod".to_string()));
+    }
+
+    #[test]
+    fn labels_fallbacks_and_hidden_values() {
+        let entry: EnvVarEntry = toml::from_str(
+            r#"
+            variable = "APP_ENV"
+            fallback_variable = "NODE_ENV"
+            format = "[env](bold red) $value"
+            labels = { production = "PROD" }
+            hide = ["dev*", "*-local"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            render(&entry, &[("APP_ENV", ""), ("NODE_ENV", "production")]),
+            Some(Rendered {
+                segments: vec![
+                    ("env".to_string(), Some(Color::Red.bold())),
+                    (" PROD".to_string(), None),
+                ],
+                description: "The value of NODE_ENV".to_string(),
+                reason: "NODE_ENV is set".to_string(),
+            })
+        );
+        assert_eq!(render(&entry, &[("APP_ENV", "development")]), None);
+        assert_eq!(render(&entry, &[("APP_ENV", "qa-local")]), None);
+        assert_eq!(render(&entry, &[]), None);
+    }
+
+    #[test]
+    fn patterns_match_any_text_for_stars() {
+        assert!(matches_pattern("eu-central-1", "eu-central-1"));
+        assert!(!matches_pattern("eu-central-1", "eu-central-10"));
+        assert!(matches_pattern("eu-*", "eu-west-2"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("a*b*c", "a-b-b-c"));
+        assert!(!matches_pattern("a*b*c", "a-c-b"));
+    }
+}
@@ -0,0 +1,52 @@
+use serde::Deserialize;
+
+use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[fill]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct FillConfig {
+    /// Repeated to fill the line
+    symbol: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for FillConfig {
+    fn default() -> Self {
+        Self {
+            symbol: ".".to_string(),
+            style: ThemedStyle::new("muted"),
+        }
+    }
+}
+
+/// Creates a module that fills the rest of its line, pushing the modules after
+/// it to the right edge of the terminal
+///
+/// The module only holds a single symbol, which the layout of the prompt repeats
+/// once the width of the rest of the line is known. It is hidden when the shell
+/// doesn't pass the width of the terminal.
+pub fn module(context: &Context) -> Option<Module> {
+    let config: FillConfig = context.config.get_module_config("fill");
+
+    context
+        .properties
+        .get("terminal_width")?
+        .parse::<usize>()
+        .ok()?;
+    if config.symbol.is_empty() {
+        return None;
+    }
+
+    let mut module = context.new_module();
+    module.set_style(config.style.resolve(&context.palette));
+    module.append_segment_str(&config.symbol);
+    module.get_suffix().set_value("");
+    module.set_description("Fills the line up to the modules after it");
+    module.set_reason("the terminal width is known");
+
+    Some(module)
+}
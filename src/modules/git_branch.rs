@@ -1,20 +1,44 @@
-use ansi_term::Color;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[git_branch]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct GitBranchConfig {
+    /// Printed before the branch name
+    symbol: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for GitBranchConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "\u{e0a0}".to_string(),
+            style: ThemedStyle::new("vcs"),
+        }
+    }
+}
 
 /// Creates a module with the Git branch in the current directory
 ///
 /// Will display the branch name if the current directory is a git repo
 pub fn module(context: &Context) -> Option<Module> {
+    let config: GitBranchConfig = context.config.get_module_config("git_branch");
     let mut module = context.new_module();
-    module.set_style(Color::Blue);
+    module.set_style(config.style.resolve(&context.palette));
 
-    module.append_segment_str("");
+    module.append_segment_str(&config.symbol);
 
     let repo = context.get_repo().ok()?;
     let branch_name = repo.branch.as_ref()?;
 
     module.append_segment_str(branch_name);
+    module.set_description(format!("The checked out git branch, {}", branch_name));
+    module.set_reason("the current directory is in a git repository");
 
     Some(module)
 }
@@ -1,26 +1,65 @@
-use ansi_term::Color;
 use git2::RepositoryState;
-use std::path::{Path, PathBuf};
+use serde::Deserialize;
+use std::path::Path;
 
 use super::{Context, Module};
+use crate::style::{deserialize_optional_style, deserialize_style, ThemedStyle};
+
+/// Options for the `[git_state]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct GitStateConfig {
+    rebase: String,
+    merge: String,
+    revert: String,
+    cherry_pick: String,
+    bisect: String,
+    am: String,
+    am_or_rebase: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+    /// The style of the progress of the operation, such as `3/10`, or the style
+    /// of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    progress_style: Option<ThemedStyle>,
+}
+
+impl Default for GitStateConfig {
+    fn default() -> Self {
+        Self {
+            rebase: "REBASING".to_string(),
+            merge: "MERGING".to_string(),
+            revert: "REVERTING".to_string(),
+            cherry_pick: "CHERRY-PICKING".to_string(),
+            bisect: "BISECTING".to_string(),
+            am: "AM".to_string(),
+            am_or_rebase: "AM/REBASE".to_string(),
+            style: ThemedStyle::new("vcs bold"),
+            progress_style: None,
+        }
+    }
+}
 
 /// Creates a module with the state of the git repository at the current directory
 ///
 /// During a git operation it will show: REBASING, BISECTING, MERGING, etc.
 /// If the progress information is available (e.g. rebasing 3/10), it will show that too.
+/// The label of each operation can be changed in the config file.
 pub fn module(context: &Context) -> Option<Module> {
+    let config: GitStateConfig = context.config.get_module_config("git_state");
     let mut module = context.new_module();
 
     let repo = context.get_repo().ok()?;
     let repo_root = repo.root.as_ref()?;
     let repo_state = repo.state?;
-    let state_description = get_state_description(repo_state, repo_root);
+    let state_description = get_state_description(repo_state, repo_root, &config);
 
     if let StateDescription::Clean = state_description {
         return None;
     }
 
-    module.set_style(Color::Blue.bold());
+    module.set_style(config.style.resolve(&context.palette));
     module.get_prefix().set_value("");
     module.get_suffix().set_value(" ");
 
@@ -31,66 +70,47 @@ pub fn module(context: &Context) -> Option<Module> {
         _ => panic!("Expected to have a label at this point in the control flow."),
     };
 
-    module.append_segment_str(label.message_default);
+    module.append_segment_str(label);
+    module.set_description(format!("A git operation is in progress: {}", label));
+    module.set_reason("the repository is not in a clean state");
 
     if let StateDescription::LabelAndProgress(_, progress) = state_description {
-        module.append_segment_str(&format!(" {}", progress.current));
-        module.append_segment_str("/");
-        module.append_segment_str(&format!("{}", progress.total));
+        module.append_segment_str(" ");
+        let progress = format!("{}/{}", progress.current, progress.total);
+        match &config.progress_style {
+            Some(style) => module.append_segment_styled(&progress, style.resolve(&context.palette)),
+            None => module.append_segment_str(&progress),
+        }
     }
 
     Some(module)
 }
 
-static MERGE_LABEL: StateLabel = StateLabel {
-    message_default: "MERGING",
-};
-
-static REVERT_LABEL: StateLabel = StateLabel {
-    message_default: "REVERTING",
-};
-
-static CHERRY_LABEL: StateLabel = StateLabel {
-    message_default: "CHERRY-PICKING",
-};
-
-static BISECT_LABEL: StateLabel = StateLabel {
-    message_default: "BISECTING",
-};
-
-static AM_LABEL: StateLabel = StateLabel {
-    message_default: "AM",
-};
-
-static REBASE_LABEL: StateLabel = StateLabel {
-    message_default: "REBASING",
-};
-
-static AM_OR_REBASE_LABEL: StateLabel = StateLabel {
-    message_default: "AM/REBASE",
-};
-
 /// Returns the state of the current repository
 ///
 /// During a git operation it will show: REBASING, BISECTING, MERGING, etc.
-fn get_state_description(state: RepositoryState, root: &PathBuf) -> StateDescription {
+fn get_state_description<'a>(
+    state: RepositoryState,
+    root: &Path,
+    config: &'a GitStateConfig,
+) -> StateDescription<'a> {
     match state {
         RepositoryState::Clean => StateDescription::Clean,
-        RepositoryState::Merge => StateDescription::Label(&MERGE_LABEL),
-        RepositoryState::Revert => StateDescription::Label(&REVERT_LABEL),
-        RepositoryState::RevertSequence => StateDescription::Label(&REVERT_LABEL),
-        RepositoryState::CherryPick => StateDescription::Label(&CHERRY_LABEL),
-        RepositoryState::CherryPickSequence => StateDescription::Label(&CHERRY_LABEL),
-        RepositoryState::Bisect => StateDescription::Label(&BISECT_LABEL),
-        RepositoryState::ApplyMailbox => StateDescription::Label(&AM_LABEL),
-        RepositoryState::ApplyMailboxOrRebase => StateDescription::Label(&AM_OR_REBASE_LABEL),
-        RepositoryState::Rebase => describe_rebase(root),
-        RepositoryState::RebaseInteractive => describe_rebase(root),
-        RepositoryState::RebaseMerge => describe_rebase(root),
+        RepositoryState::Merge => StateDescription::Label(&config.merge),
+        RepositoryState::Revert => StateDescription::Label(&config.revert),
+        RepositoryState::RevertSequence => StateDescription::Label(&config.revert),
+        RepositoryState::CherryPick => StateDescription::Label(&config.cherry_pick),
+        RepositoryState::CherryPickSequence => StateDescription::Label(&config.cherry_pick),
+        RepositoryState::Bisect => StateDescription::Label(&config.bisect),
+        RepositoryState::ApplyMailbox => StateDescription::Label(&config.am),
+        RepositoryState::ApplyMailboxOrRebase => StateDescription::Label(&config.am_or_rebase),
+        RepositoryState::Rebase => describe_rebase(root, &config.rebase),
+        RepositoryState::RebaseInteractive => describe_rebase(root, &config.rebase),
+        RepositoryState::RebaseMerge => describe_rebase(root, &config.rebase),
     }
 }
 
-fn describe_rebase(root: &PathBuf) -> StateDescription {
+fn describe_rebase<'a>(root: &Path, rebase_label: &'a str) -> StateDescription<'a> {
     /*
      *  Sadly, libgit2 seems to have some issues with reading the state of
      *  interactive rebases. So, instead, we'll poke a few of the .git files
@@ -99,7 +119,7 @@ fn describe_rebase(root: &PathBuf) -> StateDescription {
      *  The following is based heavily on: https://github.com/magicmonty/bash-git-prompt
      */
 
-    let just_label = StateDescription::Label(&REBASE_LABEL);
+    let just_label = StateDescription::Label(rebase_label);
 
     let dot_git = root.join(".git");
 
@@ -131,18 +151,14 @@ fn describe_rebase(root: &PathBuf) -> StateDescription {
 
     match progress {
         None => just_label,
-        Some(progress) => StateDescription::LabelAndProgress(&REBASE_LABEL, progress),
+        Some(progress) => StateDescription::LabelAndProgress(rebase_label, progress),
     }
 }
 
-enum StateDescription {
+enum StateDescription<'a> {
     Clean,
-    Label(&'static StateLabel),
-    LabelAndProgress(&'static StateLabel, StateProgress),
-}
-
-struct StateLabel {
-    message_default: &'static str,
+    Label(&'a str),
+    LabelAndProgress(&'a str, StateProgress),
 }
 
 struct StateProgress {
@@ -1,7 +1,68 @@
-use ansi_term::Color;
+use ansi_term::Style;
 use git2::{Repository, Status};
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_optional_style, deserialize_style, ThemedStyle};
+
+/// Options for the `[git_status]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct GitStatusConfig {
+    conflicted: String,
+    stashed: String,
+    deleted: String,
+    renamed: String,
+    modified: String,
+    staged: String,
+    untracked: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+    /// The style of the `conflicted` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    conflicted_style: Option<ThemedStyle>,
+    /// The style of the `stashed` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    stashed_style: Option<ThemedStyle>,
+    /// The style of the `deleted` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    deleted_style: Option<ThemedStyle>,
+    /// The style of the `renamed` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    renamed_style: Option<ThemedStyle>,
+    /// The style of the `modified` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    modified_style: Option<ThemedStyle>,
+    /// The style of the `staged` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    staged_style: Option<ThemedStyle>,
+    /// The style of the `untracked` symbol, or the style of the module if unset
+    #[serde(deserialize_with = "deserialize_optional_style")]
+    untracked_style: Option<ThemedStyle>,
+}
+
+impl Default for GitStatusConfig {
+    fn default() -> Self {
+        Self {
+            conflicted: "!".to_string(),
+            stashed: "+stash+ ".to_string(),
+            deleted: "D".to_string(),
+            renamed: "R".to_string(),
+            modified: "M".to_string(),
+            staged: "+".to_string(),
+            untracked: "U".to_string(),
+            style: ThemedStyle::new("error"),
+            conflicted_style: None,
+            stashed_style: None,
+            deleted_style: None,
+            renamed_style: None,
+            modified_style: None,
+            staged_style: None,
+            untracked_style: None,
+        }
+    }
+}
 
 /// Creates a module with the Git branch in the current directory
 ///
@@ -15,16 +76,28 @@ use super::{Context, Module};
 ///   - `+` — A new file has been added to the staging area
 ///   - `R` — A renamed file has been added to the staging area
 ///   - `D` — A file's deletion has been added to the staging area
+///
+/// Each symbol can be changed in the config file.
 pub fn module(context: &Context) -> Option<Module> {
+    let config: GitStatusConfig = context.config.get_module_config("git_status");
     let repo = context.get_repo().ok()?;
     let repo_root = repo.root.as_ref()?;
     let repository = Repository::open(repo_root).ok()?;
 
     let mut module = context.new_module();
 
-    module.get_prefix().set_value("").set_style(Color::Red);
-    module.get_suffix().set_value(" ").set_style(Color::Red);
-    module.set_style(Color::Red);
+    module
+        .get_prefix()
+        .set_value("")
+        .set_style(config.style.resolve(&context.palette));
+    module
+        .get_suffix()
+        .set_value(" ")
+        .set_style(config.style.resolve(&context.palette));
+    module.set_style(config.style.resolve(&context.palette));
+
+    let symbol_style =
+        |style: &Option<ThemedStyle>| style.as_ref().map(|style| style.resolve(&context.palette));
 
     let stash_object = repository.revparse_single("refs/stash");
     if stash_object.is_ok() {
@@ -38,36 +111,112 @@ pub fn module(context: &Context) -> Option<Module> {
 
     // Add the conflicted segment
     if let Ok(repo_status) = repo_status {
-        create_segment_with_count(&mut module, repo_status.conflicted, "!");
+        create_segment_with_count(
+            &mut module,
+            repo_status.conflicted,
+            &config.conflicted,
+            symbol_style(&config.conflicted_style),
+        );
     }
 
     // Add the stashed segment
     if stash_object.is_ok() {
-        module.append_segment_str("+stash+ ");
+        append_symbol(
+            &mut module,
+            &config.stashed,
+            symbol_style(&config.stashed_style),
+        );
     }
 
     // Add all remaining status segments
     if let Ok(repo_status) = repo_status {
-        create_segment_with_count(&mut module, repo_status.deleted, "D");
-        create_segment_with_count(&mut module, repo_status.renamed, "R");
-        create_segment_with_count(&mut module, repo_status.modified, "M");
-        create_segment_with_count(&mut module, repo_status.staged, "+");
-        create_segment_with_count(&mut module, repo_status.untracked, "U");
+        let symbols = [
+            (
+                repo_status.deleted,
+                &config.deleted,
+                symbol_style(&config.deleted_style),
+            ),
+            (
+                repo_status.renamed,
+                &config.renamed,
+                symbol_style(&config.renamed_style),
+            ),
+            (
+                repo_status.modified,
+                &config.modified,
+                symbol_style(&config.modified_style),
+            ),
+            (
+                repo_status.staged,
+                &config.staged,
+                symbol_style(&config.staged_style),
+            ),
+            (
+                repo_status.untracked,
+                &config.untracked,
+                symbol_style(&config.untracked_style),
+            ),
+        ];
+        for (count, symbol, style) in symbols {
+            create_segment_with_count(&mut module, count, symbol, style);
+        }
     }
 
     if module.is_empty() {
         return None;
     }
 
+    module.set_description(describe_status(
+        repo_status.unwrap_or_default(),
+        stash_object.is_ok(),
+    ));
+    module.set_reason("the git repository has changes or stashes");
+
     Some(module)
 }
 
-fn create_segment_with_count<'a>(module: &mut Module, count: usize, symbol: &str) {
+fn create_segment_with_count(
+    module: &mut Module,
+    count: usize,
+    symbol: &str,
+    style: Option<Style>,
+) {
     if count > 0 {
-        module.append_segment_str(symbol);
+        append_symbol(module, symbol, style);
+    }
+}
+
+/// Append a symbol drawn with its own style if it has one, or else the module's
+fn append_symbol(module: &mut Module, symbol: &str, style: Option<Style>) {
+    match style {
+        Some(style) => module.append_segment_styled(symbol, style),
+        None => module.append_segment_str(symbol),
     }
 }
 
+/// Describes the counts behind each symbol of the module
+fn describe_status(repo_status: RepoStatus, stashed: bool) -> String {
+    let counts = [
+        (repo_status.conflicted, "conflicted"),
+        (repo_status.deleted, "deleted"),
+        (repo_status.renamed, "renamed"),
+        (repo_status.modified, "modified"),
+        (repo_status.staged, "staged"),
+        (repo_status.untracked, "untracked"),
+    ];
+
+    let mut parts: Vec<String> = counts
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{} {}", count, label))
+        .collect();
+    if stashed {
+        parts.push("a stash".to_string());
+    }
+
+    format!("Working tree status: {}", parts.join(", "))
+}
+
 /// Gets the number of files in various git states (staged, modified, deleted, etc...)
 fn get_repo_status(repository: &Repository) -> Result<RepoStatus, git2::Error> {
     let mut status_options = git2::StatusOptions::new();
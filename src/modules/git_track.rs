@@ -1,7 +1,37 @@
-use ansi_term::Color;
 use git2::Repository;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[git_track]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct GitTrackConfig {
+    ahead: String,
+    behind: String,
+    /// The style of the ahead count
+    #[serde(deserialize_with = "deserialize_style")]
+    ahead_style: ThemedStyle,
+    /// The style of the behind count
+    #[serde(deserialize_with = "deserialize_style")]
+    behind_style: ThemedStyle,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for GitTrackConfig {
+    fn default() -> Self {
+        Self {
+            ahead: "⇡".to_string(),
+            behind: "⇣".to_string(),
+            ahead_style: ThemedStyle::new("success"),
+            behind_style: ThemedStyle::new("error"),
+            style: ThemedStyle::new("text"),
+        }
+    }
+}
 
 /// Creates a module with the Git branch in the current directory
 ///
@@ -10,31 +40,60 @@ use super::{Context, Module};
 ///   - `⇡` – This branch is ahead of the branch being tracked
 ///   - `⇣` – This branch is behind of the branch being tracked
 pub fn module(context: &Context) -> Option<Module> {
+    let config: GitTrackConfig = context.config.get_module_config("git_track");
     let repo = context.get_repo().ok()?;
     let branch_name = repo.branch.as_ref()?;
     let repo_root = repo.root.as_ref()?;
     let repository = Repository::open(repo_root).ok()?;
 
-    let module_style = Color::White;
     let mut module = context.new_module();
-    module.set_style(module_style);
+    module.set_style(config.style.resolve(&context.palette));
 
     // Add the ahead/behind segment
     match get_ahead_behind(&repository, branch_name) {
         Ok((0, 0)) => None,
         Ok((ahead, behind)) => {
             if ahead > 0 {
-                module.append_segment_str(&format!("⇡{}", ahead));
+                module.append_segment_styled(
+                    &format!("{}{}", config.ahead, ahead),
+                    config.ahead_style.resolve(&context.palette),
+                );
             }
             if behind > 0 {
-                module.append_segment_str(&format!("⇣{}", behind));
+                module.append_segment_styled(
+                    &format!("{}{}", config.behind, behind),
+                    config.behind_style.resolve(&context.palette),
+                );
             }
+            module.set_description(format!(
+                "{} the upstream branch",
+                describe_ahead_behind(ahead, behind)
+            ));
+            module.set_reason(format!(
+                "{} is {} its upstream branch",
+                branch_name,
+                describe_ahead_behind(ahead, behind)
+            ));
             Some(module)
         }
         _ => None,
     }
 }
 
+/// How far a branch is from its upstream branch, such as `2 commits ahead of`
+fn describe_ahead_behind(ahead: usize, behind: usize) -> String {
+    let commits = |count| match count {
+        1 => "1 commit".to_string(),
+        count => format!("{} commits", count),
+    };
+
+    match (ahead, behind) {
+        (ahead, 0) => format!("{} ahead of", commits(ahead)),
+        (0, behind) => format!("{} behind", commits(behind)),
+        (ahead, behind) => format!("{} ahead of and {} behind", commits(ahead), commits(behind)),
+    }
+}
+
 /// Compares the current branch with the branch it is tracking to determine how
 /// far ahead or behind it is in relation
 fn get_ahead_behind(
@@ -50,3 +109,18 @@ fn get_ahead_behind(
 
     repository.graph_ahead_behind(branch_oid, tracking_oid)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_only_the_directions_that_differ() {
+        assert_eq!(describe_ahead_behind(2, 0), "2 commits ahead of");
+        assert_eq!(describe_ahead_behind(0, 1), "1 commit behind");
+        assert_eq!(
+            describe_ahead_behind(1, 3),
+            "1 commit ahead of and 3 commits behind"
+        );
+    }
+}
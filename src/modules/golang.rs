@@ -1,7 +1,28 @@
-use ansi_term::Color;
-use std::process::Command;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+use crate::utils;
+
+/// Options for the `[golang]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct GolangConfig {
+    /// Printed before the Go version
+    symbol: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for GolangConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "+Go ".to_string(),
+            style: ThemedStyle::new("accent dimmed"),
+        }
+    }
+}
 
 /// Creates a module with the current Go version
 ///
@@ -10,44 +31,41 @@ use super::{Context, Module};
 ///     - Current directory contains a `go.sum` file
 ///     - Current directory contains a file with the `.go` extension
 pub fn module(context: &Context) -> Option<Module> {
-    let is_go_project = context
+    let go_file = context
         .try_begin_scan()?
         .set_files(&["go.mod", "go.sum"])
         .set_extensions(&["go"])
-        .is_match();
-
-    if !is_go_project {
-        return None;
-    }
+        .find_match()?;
 
+    let config: GolangConfig = context.config.get_module_config("golang");
     let mut module = context.new_module();
 
-    module.set_style(Color::Cyan.dimmed());
-    module.append_segment_str("+Go ");
+    module.set_style(config.style.resolve(&context.palette));
+    module.append_segment_str(&config.symbol);
 
-    if let Some(go_version) = get_go_version() {
-        if let Some(ver) = format_go_version(&go_version) {
-            module.append_segment_str(&ver);
-        }
+    // Not cached: the `go` binary runs a different toolchain depending on
+    // `GOTOOLCHAIN` and the `toolchain` line of the nearest go.mod
+    let go_version = get_go_version(context).and_then(|go_version| format_go_version(&go_version));
+    if let Some(ver) = go_version {
+        module.append_segment_str(&ver);
     }
+    module.set_description("The current directory is a Go project, and the installed Go version");
+    module.set_reason(format!("{} found", go_file.file_name()?.to_string_lossy()));
 
     Some(module)
 }
 
-fn get_go_version() -> Option<String> {
-    Command::new("go")
-        .arg("version")
-        .output()
-        .ok()
-        .and_then(|output| String::from_utf8(output.stdout).ok())
+fn get_go_version(context: &Context) -> Option<String> {
+    utils::exec_cmd("go", &["version"], context.config.command_timeout())
+        .map(|output| output.stdout)
 }
 
 fn format_go_version(go_stdout: &str) -> Option<String> {
     let version = go_stdout
-        // split into ["", "1.12.4 linux/amd64"]
-        .splitn(2, "go version go")
+        // split into ("", "1.12.4 linux/amd64")
+        .split_once("go version go")?
         // return "1.12.4 linux/amd64"
-        .nth(1)?
+        .1
         // split into ["1.12.4", "linux/amd64"]
         .split_whitespace()
         // return "1.12.4"
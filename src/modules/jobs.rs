@@ -1,12 +1,34 @@
-use ansi_term::Color;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[jobs]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct JobsConfig {
+    /// Printed before the number of jobs
+    symbol: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            symbol: " +".to_string(),
+            style: ThemedStyle::new("info bold"),
+        }
+    }
+}
 
 /// Creates a segment to show if there are any active jobs running
 pub fn module(context: &Context) -> Option<Module> {
+    let config: JobsConfig = context.config.get_module_config("jobs");
     let mut module = context.new_module();
 
-    module.set_style(Color::Blue.bold());
+    module.set_style(config.style.resolve(&context.palette));
 
     let props = &context.properties;
     let num_of_jobs = props
@@ -20,8 +42,13 @@ pub fn module(context: &Context) -> Option<Module> {
         return None;
     }
 
-    module.append_segment_str(" +");
+    module.append_segment_str(&config.symbol);
     module.append_segment_str(&num_of_jobs.to_string());
+    module.set_description(format!(
+        "{} jobs are running in the background",
+        num_of_jobs
+    ));
+    module.set_reason("the shell has background jobs");
 
     Some(module)
 }
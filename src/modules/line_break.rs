@@ -6,6 +6,8 @@ pub fn module(context: &Context) -> Option<Module> {
 
     module.append_segment_str("\n");
     module.get_suffix().set_value("");
+    module.set_description("Starts a new line");
+    module.set_reason("line breaks are always shown");
 
     Some(module)
 }
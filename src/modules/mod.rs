@@ -1,20 +1,55 @@
 // While adding out new module add out module to src/module.rs ALL_MODULES const array also.
-pub mod aws;
 pub mod cmd_duration;
 pub mod directory;
+pub mod env_var;
+pub mod fill;
 pub mod git_branch;
 pub mod git_state;
 pub mod git_status;
 pub mod git_track;
 pub mod golang;
 pub mod jobs;
-pub mod kube;
 pub mod line_break;
-pub mod plaio;
 pub mod prompt;
 pub mod python;
 pub mod rust;
-pub mod tailscale;
 
 use crate::context::Context;
 use crate::module::Module;
+
+pub fn handle(module: &str, context: &Context) -> Option<Module> {
+    match module {
+        // Modules defined as presets of env_var
+        "aws" | "kube" | "plaio" | "tailscale" => env_var::preset(module, context),
+        "cmd_duration" => cmd_duration::module(context),
+        "directory" => directory::module(context),
+        "env_var" => env_var::module(context),
+        "fill" => fill::module(context),
+        "git_branch" => git_branch::module(context),
+        "git_state" => git_state::module(context),
+        "git_status" => git_status::module(context),
+        "git_track" => git_track::module(context),
+        "golang" => golang::module(context),
+        "jobs" => jobs::module(context),
+        "line_break" => line_break::module(context),
+        "prompt" => prompt::module(context),
+        "python" => python::module(context),
+        "rust" => rust::module(context),
+
+        _ => {
+            log::warn!("Unknown module: {}", module);
+            None
+        }
+    }
+}
+
+/// Whether a module can be shown in the current directory at all, which is
+/// cheap to check even for modules that are slow to render.
+pub fn may_apply(module: &str, context: &Context) -> bool {
+    match module {
+        "git_branch" | "git_state" | "git_status" | "git_track" => {
+            context.get_repo().is_ok_and(|repo| repo.root.is_some())
+        }
+        _ => true,
+    }
+}
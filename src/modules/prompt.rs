@@ -1,16 +1,52 @@
-use ansi_term::Color;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[prompt]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct PromptConfig {
+    /// Shown when the last command succeeded
+    success_symbol: String,
+    /// Shown when the last command failed
+    error_symbol: String,
+    /// The style used when the last command succeeded
+    #[serde(deserialize_with = "deserialize_style")]
+    success_style: ThemedStyle,
+    /// The style used when the last command failed
+    #[serde(deserialize_with = "deserialize_style")]
+    error_style: ThemedStyle,
+    /// Shown while a command continues over several lines
+    continuation_symbol: String,
+    /// The style of the continuation symbol
+    #[serde(deserialize_with = "deserialize_style")]
+    continuation_style: ThemedStyle,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        Self {
+            success_symbol: "<$>".to_string(),
+            error_symbol: "</>".to_string(),
+            success_style: ThemedStyle::new("success bold"),
+            error_style: ThemedStyle::new("error bold"),
+            continuation_symbol: "<.>".to_string(),
+            continuation_style: ThemedStyle::new("muted"),
+        }
+    }
+}
 
 /// Creates a module for the prompt character
 ///
-/// The character segment prints an arrow character in a color dependant on the exit-
-/// code of the last executed command:
-/// - If the exit-code was "0", the arrow will be formatted with `style_success`
-/// (green by default)
-/// - If the exit-code was anything else, the arrow will be formatted with
-/// `style_failure` (red by default)
+/// The character segment prints a symbol in a color dependant on the exit-code
+/// of the last executed command:
+/// - If the exit-code was "0", `success_symbol` is shown in `success_style`,
+///   which defaults to the palette's `success` slot
+/// - If the exit-code was anything else, `error_symbol` is shown in
+///   `error_style`, which defaults to the palette's `error` slot
 pub fn module(context: &Context) -> Option<Module> {
+    let config: PromptConfig = context.config.get_module_config("prompt");
     let mut module = context.new_module();
 
     let props = &context.properties;
@@ -19,12 +55,27 @@ pub fn module(context: &Context) -> Option<Module> {
     let exit_success = exit_code == "0";
 
     if exit_success {
-        module.set_style(Color::Green.bold());
-        module.append_segment_str("<$>");
+        module.set_style(config.success_style.resolve(&context.palette));
+        module.append_segment_str(&config.success_symbol);
+        module.set_description("The last command succeeded");
     } else {
-        module.set_style(Color::Red.bold());
-        module.append_segment_str("</>");
+        module.set_style(config.error_style.resolve(&context.palette));
+        module.append_segment_str(&config.error_symbol);
+        module.set_description(format!("The last command failed with status {}", exit_code));
     };
+    module.set_reason("the prompt is always shown");
 
     Some(module)
 }
+
+/// Creates the continuation prompt, shown while a command continues over
+/// several lines
+pub fn continuation_module(context: &Context) -> Module {
+    let config: PromptConfig = context.config.get_module_config("prompt");
+    let mut module = context.new_module();
+
+    module.set_style(config.continuation_style.resolve(&context.palette));
+    module.append_segment_str(&config.continuation_symbol);
+
+    module
+}
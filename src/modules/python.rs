@@ -1,9 +1,30 @@
-use ansi_term::{Color, Style};
+use serde::Deserialize;
 use std::env;
 use std::path::Path;
-use std::process::Command;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+use crate::{cache, utils};
+
+/// Options for the `[python]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct PythonConfig {
+    /// Printed before the Python version
+    symbol: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for PythonConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "+Py ".to_string(),
+            style: ThemedStyle::new("text strikethrough bg:error"),
+        }
+    }
+}
 
 /// Creates a module with the current Python version
 ///
@@ -14,44 +35,43 @@ pub fn module(context: &Context) -> Option<Module> {
         return None;
     }
 
+    let config: PythonConfig = context.config.get_module_config("python");
     let mut module = context.new_module();
 
-    module.set_style(Style::new().fg(Color::White).on(Color::Red).strikethrough());
-    module.append_segment_str("+Py ");
+    module.set_style(config.style.resolve(&context.palette));
+    module.append_segment_str(&config.symbol);
 
-    let python_version = get_python_version()?;
-    let formatted_version = format_python_version(&python_version);
+    let formatted_version = cache::get_or_compute("python", || {
+        get_python_version(context).map(|python_version| format_python_version(&python_version))
+    })?;
     module.append_segment_str(&formatted_version);
 
     if let Some(virtual_env) = get_python_virtual_env() {
         module.append_segment_str(&format!(" ({})", virtual_env));
     };
+    module.set_description("The Python version, and the active virtualenv if any");
+    module.set_reason("PIPENV_ACTIVE is set");
 
     Some(module)
 }
 
-fn get_python_version() -> Option<String> {
-    match Command::new("python").arg("--version").output() {
-        Ok(output) => {
-            if !output.status.success() {
-                log::warn!(
-                    "Non-Zero exit code '{}' when executing `python --version`",
-                    output.status
-                );
-                return None;
-            }
-            // We have to check both stdout and stderr since for Python versions
-            // < 3.4, Python reports to stderr and for Python version >= 3.5,
-            // Python reports to stdout
-            if output.stdout.is_empty() {
-                let stderr_string = String::from_utf8(output.stderr).unwrap();
-                Some(stderr_string)
-            } else {
-                let stdout_string = String::from_utf8(output.stdout).unwrap();
-                Some(stdout_string)
-            }
-        }
-        Err(_) => None,
+fn get_python_version(context: &Context) -> Option<String> {
+    let output = utils::exec_cmd("python", &["--version"], context.config.command_timeout())?;
+
+    if !output.status.success() {
+        log::warn!(
+            "Non-Zero exit code '{}' when executing `python --version`",
+            output.status
+        );
+        return None;
+    }
+    // We have to check both stdout and stderr since for Python versions
+    // < 3.4, Python reports to stderr and for Python version >= 3.5,
+    // Python reports to stdout
+    if output.stdout.is_empty() {
+        Some(output.stderr)
+    } else {
+        Some(output.stdout)
     }
 }
 
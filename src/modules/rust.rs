@@ -1,6 +1,27 @@
-use ansi_term::Color;
+use serde::Deserialize;
 
 use super::{Context, Module};
+use crate::style::{deserialize_style, ThemedStyle};
+
+/// Options for the `[rust]` table of the config file
+#[derive(Deserialize)]
+#[serde(default)]
+struct RustConfig {
+    /// Shown when in a Rust project
+    symbol: String,
+    /// The style of the module
+    #[serde(deserialize_with = "deserialize_style")]
+    style: ThemedStyle,
+}
+
+impl Default for RustConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "+Rust".to_string(),
+            style: ThemedStyle::new("success bold"),
+        }
+    }
+}
 
 /// Creates a module with the current Rust version
 ///
@@ -8,16 +29,19 @@ use super::{Context, Module};
 ///     - Current directory contains a file with a `.rs` extension
 ///     - Current directory contains a `Cargo.toml` file
 pub fn module(context: &Context) -> Option<Module> {
-    let is_rs_project = context
+    let rs_file = context
         .try_begin_scan()?
         .set_files(&["Cargo.toml"])
         .set_extensions(&["rs"])
-        .is_match();
+        .find_match();
 
-    if is_rs_project {
+    if let Some(rs_file) = rs_file {
+        let config: RustConfig = context.config.get_module_config("rust");
         let mut module = context.new_module();
-        module.set_style(Color::Green.bold());
-        module.append_segment_str("+Rust");
+        module.set_style(config.style.resolve(&context.palette));
+        module.append_segment_str(&config.symbol);
+        module.set_description("The current directory is a Rust project");
+        module.set_reason(format!("{} found", rs_file.file_name()?.to_string_lossy()));
 
         Some(module)
     } else {
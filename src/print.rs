@@ -1,51 +1,327 @@
 use clap::ArgMatches;
-use std::fmt::Write as FmtWrite;
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::process;
+use std::sync::mpsc;
+use std::thread::{self, Scope};
+use std::time::{Duration, Instant};
 
+use crate::config::Config;
 use crate::context::Context;
+use crate::formatter::PromptFormat;
+use crate::layout;
+use crate::module::Module;
 use crate::modules;
+use crate::shell;
+use crate::theme::{Palette, Slot};
+
+/// What `render_prompt` produces from the rendered modules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// The styled prompt, escaped for the shell
+    Prompt,
+    /// The prompt with its styles removed
+    Plain,
+    /// A JSON array describing each module
+    Json,
+}
+
+impl Output {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Output::Json),
+            "plain" => Some(Output::Plain),
+            _ => None,
+        }
+    }
+}
 
 pub fn prompt(args: ArgMatches) {
+    let is_right = args.is_present("right");
+    let output = args
+        .value_of("output")
+        .and_then(Output::from_name)
+        .unwrap_or(Output::Prompt);
+    let is_continuation = args.is_present("continuation");
+    let is_transient = args.is_present("transient");
+    let skip_slow = args.is_present("fast");
     let context = Context::new(args);
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
-    write!(handle, "{}", get_prompt(context)).unwrap();
+
+    if is_continuation {
+        let module = modules::prompt::continuation_module(&context);
+        print!(
+            "{}",
+            shell::escape_prompt(&module.to_string(), context.shell)
+        );
+        return;
+    }
+
+    // The prompt left in scrollback once a command is accepted, which is just the
+    // prompt character
+    if is_transient {
+        let module = modules::handle("prompt", &context).map(|module| module.to_string());
+        print!(
+            "{}",
+            shell::escape_prompt(&module.unwrap_or_default(), context.shell)
+        );
+        return;
+    }
+
+    let mut format = if is_right {
+        right_prompt_format(&context.config, &context.palette)
+    } else {
+        prompt_format(&context.config, &context.palette)
+    };
+    format.map_styles(|style| context.color_support.apply(style));
+
+    let on_rendered = |prompt, timed_out| {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+
+        // Write a new line before the prompt, but not beside it
+        if !is_right && output == Output::Prompt {
+            writeln!(handle).unwrap();
+        }
+        write!(handle, "{}", prompt).unwrap();
+        handle.flush().unwrap();
+
+        // The modules that missed the deadline are still running, and the shell
+        // would wait for them if we let the scope join their threads.
+        if timed_out {
+            process::exit(0);
+        }
+    };
+    render_prompt(
+        &context,
+        &format,
+        skip_slow,
+        output,
+        modules::handle,
+        on_rendered,
+    );
 }
 
-pub fn get_prompt(context: Context) -> String {
-    let mut buf = String::new();
+/// Render `format` as `output`, using `handle` to render each module, and pass
+/// the result to `on_rendered`, along with whether any module missed the deadline.
+///
+/// With `skip_slow`, slow modules are shown as placeholders rather than rendered,
+/// for shells that render the full prompt in the background.
+///
+/// `on_rendered` is called before the threads of modules that missed the deadline
+/// are joined.
+pub fn render_prompt<H, F, R>(
+    context: &Context,
+    format: &PromptFormat,
+    skip_slow: bool,
+    output: Output,
+    handle: H,
+    on_rendered: F,
+) -> R
+where
+    H: Fn(&str, &Context) -> Option<Module> + Sync,
+    F: FnOnce(String, bool) -> R,
+{
+    thread::scope(|scope| {
+        let deadline = Instant::now() + Duration::from_millis(context.config.render_timeout);
+        let (mut modules, timed_out) = render_modules(
+            scope,
+            context,
+            &format.variables(),
+            skip_slow,
+            deadline,
+            &handle,
+        );
 
-    // Write a new line before the prompt
-    writeln!(buf).unwrap();
+        let terminal_width = context
+            .properties
+            .get("terminal_width")
+            .and_then(|width| width.parse().ok());
+        layout::lay_out(format, &mut modules, &context.config, terminal_width);
 
-    macro_rules! module {
-        ( $typ:ident ) => {
-            modules::$typ::module(&context)
+        let prompt = match output {
+            Output::Prompt => {
+                let prompt = format.render(|name| modules.get(name).map(Module::to_string));
+                shell::escape_prompt(&prompt, context.shell)
+            }
+            Output::Plain => {
+                let prompt = format.render(|name| modules.get(name).map(Module::to_string));
+                shell::strip_escape_sequences(&prompt)
+            }
+            Output::Json => {
+                let entries: Vec<_> = format
+                    .variables()
+                    .into_iter()
+                    .filter_map(|name| Some(modules.get(name)?.to_json(name)))
+                    .collect();
+                serde_json::Value::Array(entries).to_string()
+            }
         };
+
+        on_rendered(prompt, timed_out)
+    })
+}
+
+/// The format of the prompt, falling back to `prompt_order` if `format` isn't set
+/// or can't be parsed.
+pub fn prompt_format(config: &Config, palette: &Palette) -> PromptFormat {
+    resolve_format(config.format.as_deref(), &config.prompt_order, palette)
+}
+
+/// The format of the right prompt, falling back to `right_prompt_order` if
+/// `right_format` isn't set or can't be parsed.
+pub fn right_prompt_format(config: &Config, palette: &Palette) -> PromptFormat {
+    resolve_format(
+        config.right_format.as_deref(),
+        &config.right_prompt_order,
+        palette,
+    )
+}
+
+fn resolve_format(
+    format: Option<&str>,
+    module_order: &[String],
+    palette: &Palette,
+) -> PromptFormat {
+    match format {
+        Some(format) => PromptFormat::parse(format, palette).unwrap_or_else(|err| {
+            log::warn!("Invalid prompt format {:?} at {}", format, err);
+            PromptFormat::from_modules(module_order)
+        }),
+        None => PromptFormat::from_modules(module_order),
+    }
+}
+
+/// Render each enabled module on its own thread, and collect the output of those
+/// that finish before `deadline`.
+///
+/// Modules that render to `None` are left out of the returned map. The returned
+/// flag is set if any module missed the deadline.
+fn render_modules<'scope, 'env, H>(
+    scope: &'scope Scope<'scope, 'env>,
+    context: &'env Context,
+    names: &[&'env str],
+    skip_slow: bool,
+    deadline: Instant,
+    handle: &'env H,
+) -> (HashMap<&'env str, Module>, bool)
+where
+    H: Fn(&str, &Context) -> Option<Module> + Sync,
+{
+    let (sender, receiver) = mpsc::channel();
+
+    let mut rendered = HashMap::new();
+    let mut pending = Vec::new();
+    for &name in names {
+        if context.config.is_module_disabled(name) {
+            continue;
+        }
+
+        if skip_slow && context.config.is_module_slow(name) {
+            // Nothing is shown later for a module that doesn't apply here
+            if !modules::may_apply(name, context) {
+                continue;
+            }
+            if let Some(placeholder) = placeholder(context) {
+                rendered.insert(name, placeholder);
+            }
+            continue;
+        }
+
+        let sender = sender.clone();
+        scope.spawn(move || {
+            let output = handle(name, context);
+            // The receiver is gone if the deadline has already passed
+            sender.send((name, output)).ok();
+        });
+        pending.push(name);
+    }
+
+    while !pending.is_empty() {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(timeout) {
+            Ok((name, output)) => {
+                pending.retain(|&pending_name| pending_name != name);
+                if let Some(output) = output {
+                    rendered.insert(name, output);
+                }
+            }
+            Err(_) => {
+                log::warn!("Modules missed the render deadline: {:?}", pending);
+                break;
+            }
+        }
+    }
+
+    (rendered, !pending.is_empty())
+}
+
+/// What is shown in place of a slow module that hasn't been rendered yet
+fn placeholder(context: &Context) -> Option<Module> {
+    if context.config.async_placeholder.is_empty() {
+        return None;
+    }
+
+    let mut module = context.new_module();
+    module.set_style(context.palette.get(Slot::Muted));
+    module.append_segment_str(&context.config.async_placeholder);
+    Some(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ArgMatches;
+
+    /// Renders each module as its name, sleeping first for the modules in `slow`
+    fn fake_handler<'a>(
+        slow: &'a [(&'a str, u64)],
+    ) -> impl Fn(&str, &Context) -> Option<Module> + Sync + 'a {
+        move |name, context| {
+            if let Some(&(_, millis)) = slow.iter().find(|&&(slow_name, _)| slow_name == name) {
+                thread::sleep(Duration::from_millis(millis));
+            }
+            let mut module = context.new_module();
+            module.append_segment_str(name);
+            Some(module)
+        }
+    }
+
+    /// The rendered prompt, whether it timed out, and how long it took to be handed
+    /// to `on_rendered`
+    fn render(context: &Context, format: &str, slow: &[(&str, u64)]) -> (String, bool, Duration) {
+        let format = PromptFormat::parse(format, &Palette::default()).unwrap();
+        let started = Instant::now();
+        render_prompt(
+            context,
+            &format,
+            false,
+            Output::Plain,
+            fake_handler(slow),
+            |prompt, timed_out| (prompt, timed_out, started.elapsed()),
+        )
     }
 
-    let modules = vec![
-        module!(directory),
-        module!(git_branch),
-        module!(git_state),
-        module!(git_status),
-        module!(git_track),
-        module!(rust),
-        module!(golang),
-        module!(python),
-        module!(aws),
-        module!(plaio),
-        module!(tailscale),
-        module!(kube),
-        module!(cmd_duration),
-        module!(line_break),
-        module!(prompt),
-        module!(jobs),
-    ]; // Remove segments set to `None`
-
-    for m in modules.into_iter().filter_map(|m| m) {
-        write!(buf, "{}", m).unwrap();
-    }
-
-    buf
+    fn context_with_timeout(render_timeout: u64) -> Context<'static> {
+        let mut context = Context::new_with_dir(ArgMatches::default(), env!("CARGO_MANIFEST_DIR"));
+        context.config.render_timeout = render_timeout;
+        context
+    }
+
+    #[test]
+    fn modules_finishing_out_of_order_keep_the_format_order() {
+        let context = context_with_timeout(5000);
+        let (prompt, timed_out, _) = render(&context, "$one$two$three", &[("one", 100)]);
+        assert_eq!(prompt, "one two three ");
+        assert!(!timed_out);
+    }
+
+    #[test]
+    fn modules_missing_the_deadline_are_dropped() {
+        let context = context_with_timeout(50);
+        let (prompt, timed_out, elapsed) = render(&context, "$one$two$three", &[("two", 2000)]);
+        assert_eq!(prompt, "one three ");
+        assert!(timed_out);
+        // The prompt is handed over without waiting for the slow module
+        assert!(elapsed < Duration::from_millis(2000));
+    }
 }
@@ -6,6 +6,9 @@ use ansi_term::{ANSIString, Style};
 pub struct Segment {
     /// The string value of the current segment.
     value: String,
+
+    /// The style of the segment, if it differs from the style of its module.
+    style: Option<Style>,
 }
 
 impl Segment {
@@ -13,20 +16,117 @@ impl Segment {
     pub fn new() -> Self {
         Self {
             value: "".to_string(),
+            style: None,
         }
     }
 
     /// Sets the value of the segment.
+    ///
+    /// Values often come from outside sources, such as branch names and
+    /// environment variables, so control characters and bidi overrides are
+    /// escaped to keep them from injecting terminal escape sequences.
     pub fn set_value<T>(&mut self, value: T) -> &mut Self
     where
         T: Into<String>,
     {
-        self.value = value.into();
+        let value = value.into();
+        self.value = sanitize(&value);
+        if self.value != value {
+            log::warn!("Escaped control characters in segment value {:?}", value);
+        }
+        self
+    }
+
+    /// Sets a style for the segment, used instead of the style of its module.
+    pub fn set_style<T>(&mut self, style: T) -> &mut Self
+    where
+        T: Into<Style>,
+    {
+        self.style = Some(style.into());
         self
     }
 
+    /// Gets the style of the segment, if it has its own.
+    pub fn get_style(&self) -> Option<Style> {
+        self.style
+    }
+
+    /// Gets the value of the segment.
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
     // Returns the ANSIString of the segment value, not including its prefix and suffix
-    pub fn ansi_string(&self, style: &Style) -> ANSIString {
+    pub fn ansi_string(&self, style: &Style) -> ANSIString<'_> {
         style.paint(&self.value)
     }
 }
+
+/// Replace C0 and C1 control characters and bidi formatting characters with
+/// their `\u{..}` escapes. Line breaks are kept, as they can't change what is
+/// already on screen and the `line_break` module relies on them.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c != '\n' && (c.is_control() || is_bidi_control(c)) {
+                c.escape_unicode().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Whether `c` changes the direction of the text around it
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{61c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ansi_term::Color;
+
+    fn value_of(value: &str) -> String {
+        let mut segment = Segment::new();
+        segment.set_value(value);
+        segment.get_value().to_string()
+    }
+
+    #[test]
+    fn style_is_only_set_when_overridden() {
+        let mut segment = Segment::new();
+        assert_eq!(segment.get_style(), None);
+
+        segment.set_value("+1").set_style(Color::Green);
+        assert_eq!(segment.get_style(), Some(Color::Green.normal()));
+    }
+
+    #[test]
+    fn plain_values_are_unchanged() {
+        assert_eq!(value_of("feature/émoji-🚀"), "feature/émoji-🚀");
+        assert_eq!(value_of("\n"), "\n");
+    }
+
+    #[test]
+    fn escape_sequences_are_escaped() {
+        // A hyperlink, a title change and a screen clear
+        assert_eq!(
+            value_of("a\u{1b}]8;;https://evil\u{7}b"),
+            "a\\u{1b}]8;;https://evil\\u{7}b"
+        );
+        assert_eq!(value_of("\u{9d}0;pwned\u{9c}"), "\\u{9d}0;pwned\\u{9c}");
+        assert_eq!(value_of("main\u{1b}[2J"), "main\\u{1b}[2J");
+        assert_eq!(value_of("x\ry\u{8}\u{7f}"), "x\\u{d}y\\u{8}\\u{7f}");
+    }
+
+    #[test]
+    fn bidi_overrides_are_escaped() {
+        assert_eq!(value_of("main\u{202e}txt.exe"), "main\\u{202e}txt.exe");
+        assert_eq!(value_of("\u{2066}a\u{2069}"), "\\u{2066}a\\u{2069}");
+    }
+}
@@ -0,0 +1,338 @@
+use std::env;
+
+/// The shell that the prompt is printed for, which decides how escape sequences
+/// are marked in the output, and which characters have to be escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+    /// A plain terminal, or a shell that needs no escaping.
+    None,
+}
+
+impl Shell {
+    /// The shell named by the `--shell` flag if given, or by `STARSHIP_SHELL`,
+    /// which the init scripts set.
+    pub fn detect(flag: Option<&str>) -> Self {
+        let name = match flag {
+            Some(name) => name.to_string(),
+            None => env::var("STARSHIP_SHELL").unwrap_or_default(),
+        };
+
+        Self::from_name(&name).unwrap_or_else(|| {
+            log::debug!("Unknown shell {:?}, not escaping the prompt", name);
+            Shell::None
+        })
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "zsh" => Some(Shell::Zsh),
+            "bash" => Some(Shell::Bash),
+            "fish" => Some(Shell::Fish),
+            "none" => Some(Shell::None),
+            _ => None,
+        }
+    }
+
+    /// The markers that tell the shell the text between them takes no space on
+    /// screen, if the shell needs them.
+    fn zero_width_markers(self) -> Option<(&'static str, &'static str)> {
+        match self {
+            Shell::Zsh => Some(("%{", "%}")),
+            Shell::Bash => Some(("\\[", "\\]")),
+            // Fish works out the width of the prompt by itself
+            Shell::Fish | Shell::None => None,
+        }
+    }
+}
+
+const ESC: char = '\u{1b}';
+const BEL: char = '\u{7}';
+/// The single-character (C1) forms of `ESC [` and `ESC ]`
+const C1_CSI: char = '\u{9b}';
+const C1_OSC: char = '\u{9d}';
+const C1_ST: char = '\u{9c}';
+
+/// Escape the prompt so that the shell prints it as it is.
+///
+/// Many shells cannot deal with raw unprintable characters (like ANSI escape sequences) and
+/// miscompute the cursor position as a result, leading to strange visual bugs. Here, we wrap
+/// every escape sequence of the prompt in shell-specific markers to indicate to the shell that
+/// they are zero-length. Runs of consecutive sequences share a single pair of markers.
+///
+/// The shells also expand their own escapes in the prompt, such as `%F` in zsh and `\w` or
+/// `$HOME` in bash, so the characters that start them are escaped in the rest of the text.
+/// The zsh init script sets `prompt_subst`, so zsh expands `$(…)` like bash does.
+pub fn escape_prompt(prompt: &str, shell: Shell) -> String {
+    let (begin, end) = match shell.zero_width_markers() {
+        Some(markers) => markers,
+        None => return prompt.to_string(),
+    };
+
+    let chars: Vec<char> = prompt.chars().collect();
+    let mut output = String::with_capacity(prompt.len());
+    let mut in_sequences = false;
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        match escape_sequence_len(&chars[pos..]) {
+            Some(len) => {
+                if !in_sequences {
+                    output.push_str(begin);
+                    in_sequences = true;
+                }
+                // Sequences are expanded like the text around them, such as the
+                // `%20` of a hyperlink or the backslash that ends it with `ESC \`
+                for &c in &chars[pos..pos + len] {
+                    push_escaped(&mut output, c, shell);
+                }
+                pos += len;
+            }
+            None => {
+                if in_sequences {
+                    output.push_str(end);
+                    in_sequences = false;
+                }
+                push_escaped(&mut output, chars[pos], shell);
+                pos += 1;
+            }
+        }
+    }
+    if in_sequences {
+        output.push_str(end);
+    }
+
+    output
+}
+
+/// Remove every escape sequence from `text`, leaving only what would be printed.
+pub fn strip_escape_sequences(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len());
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        match escape_sequence_len(&chars[pos..]) {
+            Some(len) => pos += len,
+            None => {
+                output.push(chars[pos]);
+                pos += 1;
+            }
+        }
+    }
+
+    output
+}
+
+/// Push a character of text to the prompt, escaping it if the shell would
+/// otherwise expand it.
+fn push_escaped(output: &mut String, c: char, shell: Shell) {
+    match (shell, c) {
+        (Shell::Zsh, '%') => output.push_str("%%"),
+        // `prompt_subst` expands variables and commands, and reads backslashes as
+        // escapes like in double quotes
+        (Shell::Zsh, '\\') => output.push_str("\\\\"),
+        (Shell::Zsh, '$') => output.push_str("\\$"),
+        (Shell::Zsh, '`') => output.push_str("\\`"),
+        // Bash first replaces its backslash escapes, then expands variables and
+        // commands, so these need escaping for both passes
+        (Shell::Bash, '\\') => output.push_str("\\\\\\\\"),
+        (Shell::Bash, '$') => output.push_str("\\\\$"),
+        (Shell::Bash, '`') => output.push_str("\\\\`"),
+        _ => output.push(c),
+    }
+}
+
+/// The length, in characters, of the escape sequence at the start of `chars`, if
+/// there is one. Unterminated sequences run to the end of `chars`.
+fn escape_sequence_len(chars: &[char]) -> Option<usize> {
+    let (introducer_len, is_osc) = match chars {
+        [ESC, '[', ..] => (2, false),
+        [ESC, ']', ..] => (2, true),
+        [C1_CSI, ..] => (1, false),
+        [C1_OSC, ..] => (1, true),
+        // Any other escape sequence, such as `ESC 7`, is a single character long
+        [ESC, _, ..] => return Some(2),
+        [ESC] => return Some(1),
+        _ => return None,
+    };
+    let body = &chars[introducer_len..];
+
+    let body_len = if is_osc {
+        // OSC sequences end with BEL or ST, which is either `ESC \` or C1
+        let mut len = body.len();
+        for (i, &c) in body.iter().enumerate() {
+            match c {
+                BEL | C1_ST => {
+                    len = i + 1;
+                    break;
+                }
+                ESC if body.get(i + 1) == Some(&'\\') => {
+                    len = i + 2;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        len
+    } else {
+        // CSI sequences are parameter and intermediate bytes, then a final byte
+        body.iter()
+            .position(|c| ('\u{40}'..='\u{7e}').contains(c))
+            .map_or(body.len(), |i| i + 1)
+    };
+
+    Some(introducer_len + body_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::Module;
+    use ansi_term::{ANSIStrings, Color};
+
+    #[test]
+    fn detect_shell_from_flag() {
+        assert_eq!(Shell::detect(Some("bash")), Shell::Bash);
+        assert_eq!(Shell::detect(Some("fish")), Shell::Fish);
+        assert_eq!(Shell::detect(Some("tcsh")), Shell::None);
+    }
+
+    #[test]
+    fn wrap_nested_styles() {
+        let strings = [
+            Color::Red.bold().paint("a"),
+            Color::Red.bold().underline().paint("b"),
+            Color::Blue.paint("c"),
+        ];
+        let prompt = ANSIStrings(&strings).to_string();
+
+        assert_eq!(
+            escape_prompt(&prompt, Shell::Zsh),
+            "%{\u{1b}[1;31m%}a%{\u{1b}[4m%}b%{\u{1b}[0m\u{1b}[34m%}c%{\u{1b}[0m%}"
+        );
+    }
+
+    #[test]
+    fn wrap_256_and_truecolors() {
+        let prompt = Color::Fixed(208)
+            .on(Color::RGB(1, 2, 3))
+            .paint("x")
+            .to_string();
+
+        assert_eq!(
+            escape_prompt(&prompt, Shell::Bash),
+            "\\[\u{1b}[48;2;1;2;3;38;5;208m\\]x\\[\u{1b}[0m\\]"
+        );
+    }
+
+    #[test]
+    fn wrap_hyperlinks() {
+        let bel_link = "\u{1b}]8;;https://example.com\u{7}link\u{1b}]8;;\u{7}";
+        assert_eq!(
+            escape_prompt(bel_link, Shell::Zsh),
+            "%{\u{1b}]8;;https://example.com\u{7}%}link%{\u{1b}]8;;\u{7}%}"
+        );
+
+        let st_link = "\u{1b}]8;;https://example.com\u{1b}\\link\u{1b}]8;;\u{1b}\\";
+        assert_eq!(
+            escape_prompt(st_link, Shell::Bash),
+            "\\[\u{1b}]8;;https://example.com\u{1b}\\\\\\\\\\]link\\[\u{1b}]8;;\u{1b}\\\\\\\\\\]"
+        );
+        assert_eq!(
+            escape_prompt(st_link, Shell::Zsh),
+            "%{\u{1b}]8;;https://example.com\u{1b}\\\\%}link%{\u{1b}]8;;\u{1b}\\\\%}"
+        );
+    }
+
+    #[test]
+    fn escape_hyperlink_urls() {
+        let link = "\u{1b}]8;;https://e.com/a%20b?$x=`y`\u{1b}\\link\u{1b}]8;;\u{1b}\\";
+        assert_eq!(
+            escape_prompt(link, Shell::Zsh),
+            "%{\u{1b}]8;;https://e.com/a%%20b?\\$x=\\`y\\`\u{1b}\\\\%}link%{\u{1b}]8;;\u{1b}\\\\%}"
+        );
+        assert_eq!(
+            escape_prompt(link, Shell::Bash),
+            "\\[\u{1b}]8;;https://e.com/a%20b?\\\\$x=\\\\`y\\\\`\u{1b}\\\\\\\\\\]link\\[\u{1b}]8;;\u{1b}\\\\\\\\\\]"
+        );
+    }
+
+    #[test]
+    fn wrap_other_sequences() {
+        // Cursor movement, a C1 CSI and a two-character escape
+        assert_eq!(
+            escape_prompt("a\u{1b}[2Kb\u{9b}1Ac\u{1b}7", Shell::Zsh),
+            "a%{\u{1b}[2K%}b%{\u{9b}1A%}c%{\u{1b}7%}"
+        );
+        // Unterminated sequences are wrapped up to the end
+        assert_eq!(
+            escape_prompt("a\u{1b}]0;title", Shell::Zsh),
+            "a%{\u{1b}]0;title%}"
+        );
+    }
+
+    #[test]
+    fn escape_zsh_prompt_expansion() {
+        for (value, escaped) in [
+            ("fix-100%", "fix-100%%"),
+            ("%F{red}%n", "%%F{red}%%n"),
+            ("%%", "%%%%"),
+            ("a\\b", "a\\\\b"),
+            ("fix-100%$(id)`id`", "fix-100%%\\$(id)\\`id\\`"),
+        ] {
+            assert_eq!(escape_prompt(value, Shell::Zsh), escaped);
+        }
+    }
+
+    #[test]
+    fn escape_bash_prompt_expansion() {
+        for (value, escaped) in [
+            ("fix-100%", "fix-100%"),
+            ("\\w\\u", "\\\\\\\\w\\\\\\\\u"),
+            ("$HOME", "\\\\$HOME"),
+            ("$(reboot)", "\\\\$(reboot)"),
+            ("`reboot`", "\\\\`reboot\\\\`"),
+        ] {
+            assert_eq!(escape_prompt(value, Shell::Bash), escaped);
+        }
+    }
+
+    #[test]
+    fn escape_hostile_module_values() {
+        let mut module = Module::new();
+        module.set_style(Color::Blue);
+        module.append_segment_str("%F{red}$(id)\\");
+        let prompt = module.to_string();
+
+        // Only the value is escaped, never the styling around it
+        assert_eq!(
+            escape_prompt(&prompt, Shell::Zsh),
+            "%{\u{1b}[34m%}%%F{red}\\$(id)\\\\%{\u{1b}[0m%} "
+        );
+        assert_eq!(
+            escape_prompt(&prompt, Shell::Bash),
+            "\\[\u{1b}[34m\\]%F{red}\\\\$(id)\\\\\\\\\\[\u{1b}[0m\\] "
+        );
+        assert_eq!(escape_prompt(&prompt, Shell::Fish), prompt);
+    }
+
+    #[test]
+    fn strip_styles_and_links() {
+        let strings = [Color::Red.bold().paint("a"), Color::Fixed(208).paint("b")];
+        let text = format!(
+            "{}\u{1b}]8;;https://example.com\u{7}c\u{1b}]8;;\u{7}",
+            ANSIStrings(&strings)
+        );
+        assert_eq!(strip_escape_sequences(&text), "abc");
+    }
+
+    #[test]
+    fn no_wrapping_for_fish_and_plain_terminals() {
+        let prompt = Color::Red.paint("a").to_string();
+        assert_eq!(escape_prompt(&prompt, Shell::Fish), prompt);
+        assert_eq!(escape_prompt(&prompt, Shell::None), prompt);
+    }
+}
@@ -0,0 +1,407 @@
+use ansi_term::{Color, Style};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+
+use crate::theme::{Palette, Slot};
+
+/// Parse a style string such as `"bold italic fg:#ff8800 bg:236"` into a `Style`.
+///
+/// The string is a whitespace-separated list of tokens, read in order:
+///   - `bold`, `dimmed`, `italic`, `underline`, `blink`, `hidden`, `strikethrough`
+///     and `inverted` turn on a text attribute
+///   - `fg:<color>` and `bg:<color>` set the foreground and background colors,
+///     and a bare `<color>` sets the foreground color
+///   - a palette slot such as `accent` or `warning` adds the slot's style from
+///     `palette`
+///   - `none` clears everything set by the previous tokens
+///
+/// Colors are either a name (`red`, `bright-red`, ...), a number from `0` to `255`
+/// selecting a color of the 256-color palette, a `#rrggbb` truecolor hex code, a
+/// palette slot, whose foreground color is used, or `none` to unset the color.
+pub fn parse_style_string(style_string: &str, palette: &Palette) -> Result<Style, StyleError> {
+    style_string
+        .split_whitespace()
+        .try_fold(Style::new(), |style, token| {
+            let lowercase = token.to_lowercase();
+            let style = match lowercase.as_str() {
+                "none" => Style::new(),
+                "bold" => style.bold(),
+                "dimmed" => style.dimmed(),
+                "italic" => style.italic(),
+                "underline" => style.underline(),
+                "blink" => style.blink(),
+                "hidden" => style.hidden(),
+                "strikethrough" => style.strikethrough(),
+                "inverted" => style.reverse(),
+                name if Slot::from_name(name).is_some() => {
+                    let slot = Slot::from_name(name).unwrap();
+                    add_style(style, palette.get(slot))
+                }
+                _ => {
+                    let (is_background, color_string) = match lowercase.split_once(':') {
+                        Some(("fg", color_string)) => (false, color_string),
+                        Some(("bg", color_string)) => (true, color_string),
+                        Some(_) => return Err(StyleError::new(token, StyleErrorKind::Prefix)),
+                        None => (false, lowercase.as_str()),
+                    };
+                    let color = parse_color_string(color_string, palette)
+                        .map_err(|kind| StyleError::new(token, kind))?;
+
+                    if is_background {
+                        Style {
+                            background: color,
+                            ..style
+                        }
+                    } else {
+                        Style {
+                            foreground: color,
+                            ..style
+                        }
+                    }
+                }
+            };
+
+            Ok(style)
+        })
+}
+
+/// Parse a color string into a `Color`, or `None` if the string is `none`.
+fn parse_color_string(
+    color_string: &str,
+    palette: &Palette,
+) -> Result<Option<Color>, StyleErrorKind> {
+    if let Some(hex) = color_string.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(StyleErrorKind::Hex);
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        return Ok(Some(Color::RGB(channel(0), channel(2), channel(4))));
+    }
+
+    if color_string.starts_with(|c: char| c.is_ascii_digit()) {
+        return color_string
+            .parse::<u8>()
+            .map(|index| Some(Color::Fixed(index)))
+            .map_err(|_| StyleErrorKind::Index);
+    }
+
+    let (is_bright, name) = match color_string.strip_prefix("bright-") {
+        Some(name) => (true, name),
+        None => (false, color_string),
+    };
+    let (index, color) = match name {
+        "none" if !is_bright => return Ok(None),
+        "black" => (0, Color::Black),
+        "red" => (1, Color::Red),
+        "green" => (2, Color::Green),
+        "yellow" => (3, Color::Yellow),
+        "blue" => (4, Color::Blue),
+        "purple" | "magenta" => (5, Color::Purple),
+        "cyan" => (6, Color::Cyan),
+        "white" => (7, Color::White),
+        name if !is_bright => match Slot::from_name(name) {
+            Some(slot) => return Ok(palette.get(slot).foreground),
+            None => return Err(StyleErrorKind::Name),
+        },
+        _ => return Err(StyleErrorKind::Name),
+    };
+
+    if is_bright {
+        Ok(Some(Color::Fixed(index + 8)))
+    } else {
+        Ok(Some(color))
+    }
+}
+
+/// Add the attributes and colors of `added` to `style`, keeping the colors of
+/// `style` that `added` doesn't set.
+fn add_style(style: Style, added: Style) -> Style {
+    Style {
+        foreground: added.foreground.or(style.foreground),
+        background: added.background.or(style.background),
+        is_bold: style.is_bold || added.is_bold,
+        is_dimmed: style.is_dimmed || added.is_dimmed,
+        is_italic: style.is_italic || added.is_italic,
+        is_underline: style.is_underline || added.is_underline,
+        is_blink: style.is_blink || added.is_blink,
+        is_hidden: style.is_hidden || added.is_hidden,
+        is_strikethrough: style.is_strikethrough || added.is_strikethrough,
+        is_reverse: style.is_reverse || added.is_reverse,
+    }
+}
+
+/// Write a `Style` as a style string, the inverse of `parse_style_string`.
+///
+/// The default style is written as `none`.
+pub fn style_to_string(style: &Style) -> String {
+    let attributes = [
+        (style.is_bold, "bold"),
+        (style.is_dimmed, "dimmed"),
+        (style.is_italic, "italic"),
+        (style.is_underline, "underline"),
+        (style.is_blink, "blink"),
+        (style.is_hidden, "hidden"),
+        (style.is_strikethrough, "strikethrough"),
+        (style.is_reverse, "inverted"),
+    ];
+
+    let mut tokens: Vec<String> = attributes
+        .iter()
+        .filter(|(is_set, _)| *is_set)
+        .map(|(_, name)| name.to_string())
+        .collect();
+    if let Some(color) = style.foreground {
+        tokens.push(format!("fg:{}", color_to_string(color)));
+    }
+    if let Some(color) = style.background {
+        tokens.push(format!("bg:{}", color_to_string(color)));
+    }
+
+    if tokens.is_empty() {
+        "none".to_string()
+    } else {
+        tokens.join(" ")
+    }
+}
+
+fn color_to_string(color: Color) -> String {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "purple", "cyan", "white",
+    ];
+
+    match color {
+        Color::Black => NAMES[0].to_string(),
+        Color::Red => NAMES[1].to_string(),
+        Color::Green => NAMES[2].to_string(),
+        Color::Yellow => NAMES[3].to_string(),
+        Color::Blue => NAMES[4].to_string(),
+        Color::Purple => NAMES[5].to_string(),
+        Color::Cyan => NAMES[6].to_string(),
+        Color::White => NAMES[7].to_string(),
+        Color::Fixed(index @ 8..=15) => format!("bright-{}", NAMES[index as usize - 8]),
+        Color::Fixed(index) => index.to_string(),
+        Color::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+    }
+}
+
+/// A style string from the config file, such as `"accent bold"`. Its palette
+/// slots are looked up once the palette of the prompt is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThemedStyle(String);
+
+impl ThemedStyle {
+    /// A style written in the code, such as the default style of a module.
+    pub fn new(style_string: &str) -> Self {
+        Self(style_string.to_string())
+    }
+
+    /// Check that a style string from the config file can be parsed.
+    pub fn parse(style_string: &str) -> Result<Self, StyleError> {
+        parse_style_string(style_string, &Palette::default())?;
+        Ok(Self::new(style_string))
+    }
+
+    /// The style, with its palette slots taken from `palette`.
+    pub fn resolve(&self, palette: &Palette) -> Style {
+        parse_style_string(&self.0, palette).unwrap_or_else(|err| {
+            log::warn!("Invalid style {:?}: {}", self.0, err);
+            Style::default()
+        })
+    }
+}
+
+/// Deserialize a style string from the config file, for use with
+/// `#[serde(deserialize_with = "deserialize_style")]`.
+pub fn deserialize_style<'de, D>(deserializer: D) -> Result<ThemedStyle, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let style_string = String::deserialize(deserializer)?;
+    ThemedStyle::parse(&style_string).map_err(serde::de::Error::custom)
+}
+
+/// Deserialize an optional style string from the config file, for use with
+/// `#[serde(deserialize_with = "deserialize_optional_style")]`.
+pub fn deserialize_optional_style<'de, D>(deserializer: D) -> Result<Option<ThemedStyle>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_style(deserializer).map(Some)
+}
+
+/// The error returned when a style string contains a token that isn't understood.
+#[derive(Debug, PartialEq)]
+pub struct StyleError {
+    token: String,
+    kind: StyleErrorKind,
+}
+
+#[derive(Debug, PartialEq)]
+enum StyleErrorKind {
+    Prefix,
+    Hex,
+    Index,
+    Name,
+}
+
+impl StyleError {
+    fn new(token: &str, kind: StyleErrorKind) -> Self {
+        Self {
+            token: token.to_string(),
+            kind,
+        }
+    }
+}
+
+impl fmt::Display for StyleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.kind {
+            StyleErrorKind::Prefix => "only the `fg:` and `bg:` prefixes are supported",
+            StyleErrorKind::Hex => "hex colors must be written as `#rrggbb`",
+            StyleErrorKind::Index => "color numbers must be between 0 and 255",
+            StyleErrorKind::Name => {
+                "expected a text attribute, a color name, a palette slot or `none`"
+            }
+        };
+        write!(f, "invalid style token {:?}: {}", self.token, reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(style_string: &str) -> Result<Style, StyleError> {
+        parse_style_string(style_string, &Palette::default())
+    }
+
+    #[test]
+    fn parse_empty_style() {
+        assert_eq!(parse(""), Ok(Style::new()));
+        assert_eq!(parse("none"), Ok(Style::new()));
+    }
+
+    #[test]
+    fn parse_attributes() {
+        assert_eq!(
+            parse("bold italic underline dimmed"),
+            Ok(Style::new().bold().italic().underline().dimmed())
+        );
+        assert_eq!(
+            parse("Blink HIDDEN strikethrough inverted"),
+            Ok(Style::new().blink().hidden().strikethrough().reverse())
+        );
+    }
+
+    #[test]
+    fn parse_named_colors() {
+        assert_eq!(parse("bold cyan"), Ok(Color::Cyan.bold()));
+        assert_eq!(
+            parse("fg:White BG:magenta"),
+            Ok(Style::new().fg(Color::White).on(Color::Purple))
+        );
+        assert_eq!(parse("bright-red"), Ok(Color::Fixed(9).normal()));
+    }
+
+    #[test]
+    fn parse_fixed_and_truecolor() {
+        assert_eq!(
+            parse("bold italic fg:#ff8800 bg:236 underline"),
+            Ok(Style::new()
+                .bold()
+                .italic()
+                .fg(Color::RGB(0xff, 0x88, 0x00))
+                .on(Color::Fixed(236))
+                .underline())
+        );
+        assert_eq!(parse("#0A0b0C"), Ok(Color::RGB(10, 11, 12).normal()));
+    }
+
+    #[test]
+    fn parse_palette_slots() {
+        let palette = Palette::built_in("solarized").unwrap();
+        let parse = |style_string| parse_style_string(style_string, &palette);
+
+        assert_eq!(parse("accent bold"), Ok(palette.get(Slot::Accent).bold()));
+        assert_eq!(
+            parse("bold muted"),
+            Ok(add_style(Style::new().bold(), palette.get(Slot::Muted)))
+        );
+        assert_eq!(
+            parse("fg:text bg:error"),
+            Ok(Style {
+                foreground: palette.get(Slot::Text).foreground,
+                background: palette.get(Slot::Error).foreground,
+                ..Style::new()
+            })
+        );
+        assert_eq!(
+            parse("bright-accent").unwrap_err().kind,
+            StyleErrorKind::Name
+        );
+    }
+
+    #[test]
+    fn parse_none_clears_style() {
+        assert_eq!(parse("bold red none"), Ok(Style::new()));
+        assert_eq!(parse("none bold"), Ok(Style::new().bold()));
+        assert_eq!(
+            parse("red bg:blue fg:none"),
+            Ok(Style::new().on(Color::Blue))
+        );
+    }
+
+    #[test]
+    fn parse_invalid_tokens() {
+        let kind = |style_string| parse(style_string).unwrap_err().kind;
+
+        assert_eq!(kind("bold mauve"), StyleErrorKind::Name);
+        assert_eq!(kind("bright-none"), StyleErrorKind::Name);
+        assert_eq!(kind("xx:red"), StyleErrorKind::Prefix);
+        assert_eq!(kind("fg:#ff88"), StyleErrorKind::Hex);
+        assert_eq!(kind("#gggggg"), StyleErrorKind::Hex);
+        assert_eq!(kind("bg:256"), StyleErrorKind::Index);
+    }
+
+    #[test]
+    fn style_to_string_round_trips() {
+        for style_string in [
+            "none",
+            "bold fg:cyan",
+            "dimmed italic fg:bright-red bg:236",
+            "underline inverted fg:#ff8800 bg:purple",
+        ] {
+            let style = parse(style_string).unwrap();
+            assert_eq!(style_to_string(&style), style_string);
+        }
+    }
+
+    #[test]
+    fn deserialize_from_config() {
+        #[derive(Debug, Deserialize)]
+        struct TestConfig {
+            #[serde(deserialize_with = "deserialize_style")]
+            style: ThemedStyle,
+        }
+
+        let config: TestConfig = toml::from_str(r#"style = "bold red""#).unwrap();
+        assert_eq!(config.style.resolve(&Palette::default()), Color::Red.bold());
+
+        // Slots are only looked up once the palette is known
+        let config: TestConfig = toml::from_str(r#"style = "warning""#).unwrap();
+        let light = Palette::built_in("light").unwrap();
+        assert_eq!(config.style.resolve(&light), light.get(Slot::Warning));
+
+        let err = toml::from_str::<TestConfig>(r#"style = "bold mauve""#).unwrap_err();
+        assert!(err.to_string().contains("invalid style token \"mauve\""));
+    }
+
+    #[test]
+    fn error_names_token() {
+        let err = parse("bold fg:#ff88").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid style token \"fg:#ff88\": hex colors must be written as `#rrggbb`"
+        );
+    }
+}
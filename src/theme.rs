@@ -0,0 +1,260 @@
+use ansi_term::{Color, Style};
+use std::collections::HashMap;
+use std::env;
+
+use crate::config::Config;
+use crate::style::parse_style_string;
+
+/// A role that modules give their styles, which the palette of the prompt turns into a
+/// concrete style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    /// Where the prompt is, such as the current directory
+    Accent,
+    /// Information about the shell, such as background jobs
+    Info,
+    /// Version control, such as the current branch
+    Vcs,
+    /// A successful command, or a language the project is written in
+    Success,
+    /// Something worth noticing, such as a slow command or a cloud profile
+    Warning,
+    /// A failed command, or changes that need attention
+    Error,
+    /// Plain information
+    Text,
+    /// Information that should stand out from the rest
+    Highlight,
+    /// Decoration that should stay in the background
+    Muted,
+}
+
+impl Slot {
+    const ALL: [Slot; 9] = [
+        Slot::Accent,
+        Slot::Info,
+        Slot::Vcs,
+        Slot::Success,
+        Slot::Warning,
+        Slot::Error,
+        Slot::Text,
+        Slot::Highlight,
+        Slot::Muted,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|slot| slot.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Slot::Accent => "accent",
+            Slot::Info => "info",
+            Slot::Vcs => "vcs",
+            Slot::Success => "success",
+            Slot::Warning => "warning",
+            Slot::Error => "error",
+            Slot::Text => "text",
+            Slot::Highlight => "highlight",
+            Slot::Muted => "muted",
+        }
+    }
+}
+
+/// The built-in palettes, which can be chosen by name.
+const BUILT_IN: &[&str] = &["dark", "light", "solarized"];
+
+/// The style of every slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    styles: HashMap<Slot, Style>,
+}
+
+impl Palette {
+    pub fn built_in(name: &str) -> Option<Self> {
+        let style_of: fn(Slot) -> Style = match name {
+            "dark" => dark,
+            "light" => light,
+            "solarized" => solarized,
+            _ => return None,
+        };
+
+        Some(Self {
+            styles: Slot::ALL
+                .iter()
+                .map(|&slot| (slot, style_of(slot)))
+                .collect(),
+        })
+    }
+
+    /// A palette from a `[palettes.<name>]` table of the config file, where each
+    /// slot is set to a style string. Slots that aren't set keep their style from
+    /// the `dark` palette, which is also where slots named in the style strings
+    /// are looked up.
+    fn from_table(name: &str, table: &HashMap<String, String>) -> Self {
+        let dark = Self::default();
+        let mut palette = Self::default();
+
+        for (slot_name, style_string) in table {
+            let slot = match Slot::from_name(slot_name) {
+                Some(slot) => slot,
+                None => {
+                    log::warn!("Unknown slot {:?} in palette {:?}", slot_name, name);
+                    continue;
+                }
+            };
+            match parse_style_string(style_string, &dark) {
+                Ok(style) => {
+                    palette.styles.insert(slot, style);
+                }
+                Err(err) => log::warn!(
+                    "Invalid style for {:?} in palette {:?}: {}",
+                    slot_name,
+                    name,
+                    err
+                ),
+            }
+        }
+
+        palette
+    }
+
+    pub fn get(&self, slot: Slot) -> Style {
+        self.styles.get(&slot).copied().unwrap_or_default()
+    }
+}
+
+/// The `dark` palette
+impl Default for Palette {
+    fn default() -> Self {
+        Self::built_in("dark").unwrap()
+    }
+}
+
+/// Choose the palette named by `STARSHIP_THEME`, or by the `palette` option of
+/// the config file, or guess between `light` and `dark` from `COLORFGBG`.
+pub fn select(config: &Config) -> Palette {
+    let from_env = env::var("STARSHIP_THEME")
+        .ok()
+        .filter(|name| !name.is_empty());
+    let name = from_env
+        .or_else(|| config.palette.clone())
+        .or_else(|| {
+            let colorfgbg = env::var("COLORFGBG").ok()?;
+            guess_from_colorfgbg(&colorfgbg).map(str::to_string)
+        })
+        .unwrap_or_else(|| "dark".to_string());
+
+    if let Some(table) = config.palettes.get(&name) {
+        return Palette::from_table(&name, table);
+    }
+    Palette::built_in(&name).unwrap_or_else(|| {
+        log::warn!(
+            "Unknown palette {:?}, expected one of {:?} or a [palettes.{}] table",
+            name,
+            BUILT_IN,
+            name
+        );
+        Palette::default()
+    })
+}
+
+/// Some terminals set `COLORFGBG` to the palette indexes of their foreground and
+/// background colors, such as `15;0`. The background is the last of them.
+fn guess_from_colorfgbg(colorfgbg: &str) -> Option<&'static str> {
+    let background: u8 = colorfgbg.rsplit(';').next()?.parse().ok()?;
+
+    // White and the bright colors other than gray are light
+    match background {
+        7 | 9..=15 => Some("light"),
+        _ => Some("dark"),
+    }
+}
+
+fn dark(slot: Slot) -> Style {
+    match slot {
+        Slot::Accent => Color::Cyan.normal(),
+        Slot::Info => Color::Blue.normal(),
+        Slot::Vcs => Color::Blue.normal(),
+        Slot::Success => Color::Green.normal(),
+        Slot::Warning => Color::Yellow.normal(),
+        Slot::Error => Color::Red.normal(),
+        Slot::Text => Color::White.normal(),
+        Slot::Highlight => Color::Purple.normal(),
+        Slot::Muted => Style::new().dimmed(),
+    }
+}
+
+fn light(slot: Slot) -> Style {
+    match slot {
+        Slot::Accent => Color::Blue.normal(),
+        Slot::Info => Color::Cyan.normal(),
+        Slot::Vcs => Color::Purple.normal(),
+        Slot::Success => Color::Green.normal(),
+        // Yellow is hard to read on a light background
+        Slot::Warning => Color::Fixed(130).normal(),
+        Slot::Error => Color::Red.normal(),
+        Slot::Text => Color::Black.normal(),
+        Slot::Highlight => Color::Fixed(92).normal(),
+        Slot::Muted => Color::Fixed(245).normal(),
+    }
+}
+
+/// https://ethanschoonover.com/solarized/
+fn solarized(slot: Slot) -> Style {
+    match slot {
+        Slot::Accent => Color::RGB(0x2a, 0xa1, 0x98).normal(),
+        Slot::Info => Color::RGB(0x26, 0x8b, 0xd2).normal(),
+        Slot::Vcs => Color::RGB(0x6c, 0x71, 0xc4).normal(),
+        Slot::Success => Color::RGB(0x85, 0x99, 0x00).normal(),
+        Slot::Warning => Color::RGB(0xb5, 0x89, 0x00).normal(),
+        Slot::Error => Color::RGB(0xdc, 0x32, 0x2f).normal(),
+        Slot::Text => Color::RGB(0x93, 0xa1, 0xa1).normal(),
+        Slot::Highlight => Color::RGB(0xd3, 0x36, 0x82).normal(),
+        Slot::Muted => Color::RGB(0x58, 0x6e, 0x75).normal(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slot_names_round_trip() {
+        for slot in Slot::ALL {
+            assert_eq!(Slot::from_name(slot.name()), Some(slot));
+        }
+        assert_eq!(Slot::from_name("red"), None);
+    }
+
+    #[test]
+    fn built_in_palettes_set_every_slot() {
+        for name in BUILT_IN {
+            let palette = Palette::built_in(name).unwrap();
+            assert_eq!(palette.styles.len(), Slot::ALL.len());
+        }
+        assert_eq!(Palette::built_in("nope"), None);
+    }
+
+    #[test]
+    fn custom_palette_falls_back_to_dark() {
+        let mut table = HashMap::new();
+        table.insert("accent".to_string(), "bold #ff8800".to_string());
+        table.insert("nope".to_string(), "red".to_string());
+        table.insert("error".to_string(), "fg:nope".to_string());
+
+        let palette = Palette::from_table("mine", &table);
+        assert_eq!(palette.get(Slot::Accent), Color::RGB(255, 136, 0).bold());
+        assert_eq!(palette.get(Slot::Error), dark(Slot::Error));
+        assert_eq!(palette.get(Slot::Vcs), dark(Slot::Vcs));
+    }
+
+    #[test]
+    fn colorfgbg_background_decides_light_or_dark() {
+        assert_eq!(guess_from_colorfgbg("15;0"), Some("dark"));
+        assert_eq!(guess_from_colorfgbg("0;15"), Some("light"));
+        assert_eq!(guess_from_colorfgbg("0;default;7"), Some("light"));
+        assert_eq!(guess_from_colorfgbg("15;8"), Some("dark"));
+        assert_eq!(guess_from_colorfgbg("default"), None);
+    }
+}
@@ -0,0 +1,218 @@
+use clap::ArgMatches;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::context::Context;
+use crate::modules;
+use crate::print;
+
+/// The timings of a module across every run.
+#[derive(Debug, Default)]
+struct Timing {
+    durations: Vec<Duration>,
+
+    /// The part of each duration spent on work shared through the `Context`.
+    shared: Vec<Duration>,
+
+    /// Whether the module output anything in any of the runs.
+    has_output: bool,
+}
+
+impl Timing {
+    /// The duration within which `percent` percent of the runs finished, using
+    /// the nearest-rank method.
+    fn percentile(&self, percent: usize) -> Duration {
+        nearest_rank(&self.durations, percent)
+    }
+
+    /// The median time spent on shared work.
+    fn shared_median(&self) -> Duration {
+        nearest_rank(&self.shared, 50)
+    }
+}
+
+fn nearest_rank(durations: &[Duration], percent: usize) -> Duration {
+    let mut durations = durations.to_vec();
+    durations.sort();
+
+    let rank = (percent * durations.len()).div_ceil(100).max(1);
+    durations.get(rank - 1).copied().unwrap_or_default()
+}
+
+/// Render every module of the left and right prompts `repeat` times, and print
+/// how long each one took, from slowest to fastest.
+///
+/// Modules are rendered one after another rather than in parallel, so that
+/// their times don't include waiting for each other. Work shared through the
+/// `Context`, such as `get_repo`, is charged to the module that triggered it,
+/// and that part of its time is also shown on its own.
+pub fn timings(args: ArgMatches, repeat: usize) {
+    let mut module_timings: HashMap<String, Timing> = HashMap::new();
+
+    for _ in 0..repeat {
+        // A new context for every run, so that shared work isn't reused
+        let context = Context::new(args.clone());
+        let format = print::prompt_format(&context.config, &context.palette);
+        let right_format = print::right_prompt_format(&context.config, &context.palette);
+
+        let mut names: Vec<&str> = Vec::new();
+        for name in format
+            .variables()
+            .into_iter()
+            .chain(right_format.variables())
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        for name in names {
+            if context.config.is_module_disabled(name) {
+                continue;
+            }
+
+            let shared_before = shared_total(&context);
+            let start_time = Instant::now();
+            let module = modules::handle(name, &context);
+            let duration = start_time.elapsed();
+
+            let timing = module_timings.entry(name.to_string()).or_default();
+            timing.durations.push(duration);
+            timing.shared.push(shared_total(&context) - shared_before);
+            timing.has_output |= module.is_some_and(|module| !module.get_value().is_empty());
+        }
+    }
+
+    print!("{}", format_table(module_timings, repeat));
+}
+
+/// The time spent on shared work so far. Each piece of work is only done once
+/// per context, so the total grows by what the last module triggered.
+fn shared_total(context: &Context) -> Duration {
+    context
+        .shared_durations()
+        .into_iter()
+        .map(|(_, duration)| duration)
+        .sum()
+}
+
+fn format_table(module_timings: HashMap<String, Timing>, repeat: usize) -> String {
+    let modules = sort_by_cost(module_timings);
+
+    let width = modules
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Module".len());
+
+    // The shared column is the median, like the time of a single run
+    let columns = |timing: &Timing| {
+        let shared = format_duration(timing.shared_median());
+        if repeat == 1 {
+            format!(
+                "{:>10}  {:>10}",
+                format_duration(timing.percentile(50)),
+                shared
+            )
+        } else {
+            format!(
+                "{:>10}  {:>10}  {:>10}  {:>10}",
+                format_duration(timing.percentile(0)),
+                format_duration(timing.percentile(50)),
+                format_duration(timing.percentile(95)),
+                shared
+            )
+        }
+    };
+    let header = if repeat == 1 {
+        format!("{:>10}  {:>10}", "Time", "Shared")
+    } else {
+        format!(
+            "{:>10}  {:>10}  {:>10}  {:>10}",
+            "Min", "Median", "P95", "Shared"
+        )
+    };
+
+    let mut table = format!("{:width$}  {}  Output\n", "Module", header, width = width);
+    for (name, timing) in &modules {
+        let output = if timing.has_output { "yes" } else { "no" };
+        table.push_str(&format!(
+            "{:width$}  {}  {}\n",
+            name,
+            columns(timing),
+            output,
+            width = width
+        ));
+    }
+
+    table
+}
+
+/// Sort timings from the slowest median to the fastest, then by name.
+fn sort_by_cost(timings: HashMap<String, Timing>) -> Vec<(String, Timing)> {
+    let mut timings: Vec<_> = timings.into_iter().collect();
+    timings.sort_by(|(a_name, a), (b_name, b)| {
+        b.percentile(50)
+            .cmp(&a.percentile(50))
+            .then_with(|| a_name.cmp(b_name))
+    });
+    timings
+}
+
+/// Format a duration in milliseconds, e.g. `12.34ms`.
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(millis: &[u64], shared_millis: &[u64], has_output: bool) -> Timing {
+        let durations =
+            |millis: &[u64]| millis.iter().map(|&ms| Duration::from_millis(ms)).collect();
+        Timing {
+            durations: durations(millis),
+            shared: durations(shared_millis),
+            has_output,
+        }
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let ten_runs = timing(&[5, 1, 4, 2, 3, 6, 7, 8, 9, 10], &[], true);
+        assert_eq!(ten_runs.percentile(0), Duration::from_millis(1));
+        assert_eq!(ten_runs.percentile(50), Duration::from_millis(5));
+        assert_eq!(ten_runs.percentile(95), Duration::from_millis(10));
+
+        let one_run = timing(&[3], &[], true);
+        assert_eq!(one_run.percentile(95), Duration::from_millis(3));
+    }
+
+    #[test]
+    fn table_is_sorted_by_cost() {
+        let mut modules = HashMap::new();
+        modules.insert("rust".to_string(), timing(&[1], &[0], true));
+        modules.insert("git_status".to_string(), timing(&[12], &[3], false));
+
+        assert_eq!(
+            format_table(modules, 1),
+            "Module            Time      Shared  Output\n\
+             git_status     12.00ms      3.00ms  no\n\
+             rust            1.00ms      0.00ms  yes\n"
+        );
+    }
+
+    #[test]
+    fn repeated_table_shows_percentiles() {
+        let mut modules = HashMap::new();
+        modules.insert("rust".to_string(), timing(&[1, 2, 3], &[0, 1, 0], true));
+
+        assert_eq!(
+            format_table(modules, 3),
+            "Module         Min      Median         P95      Shared  Output\n\
+             rust        1.00ms      2.00ms      3.00ms      0.00ms  yes\n"
+        );
+    }
+}
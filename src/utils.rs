@@ -1,6 +1,10 @@
 use std::fs::File;
 use std::io::{Read, Result};
 use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Return the string contents of a file
 pub fn read_file<P: AsRef<Path>>(file_name: P) -> Result<String> {
@@ -10,3 +14,172 @@ pub fn read_file<P: AsRef<Path>>(file_name: P) -> Result<String> {
     file.read_to_string(&mut data)?;
     Ok(data)
 }
+
+/// The captured output of a command run with `exec_cmd`
+#[derive(Debug)]
+pub struct CommandOutput {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Run a command and capture its output, killing it if it doesn't exit within `timeout`.
+///
+/// Returns `None` if the command couldn't be started, had to be killed, or left
+/// its output open past the timeout, such as a shim whose children keep running.
+pub fn exec_cmd(cmd: &str, args: &[&str], timeout: Duration) -> Option<CommandOutput> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+    let start_time = Instant::now();
+    let mut command = Command::new(cmd);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // Run the command in its own process group, so it can be killed along with
+    // any processes it starts
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let mut child = command
+        .spawn()
+        .map_err(|err| log::debug!("Unable to run `{} {}`: {}", cmd, args.join(" "), err))
+        .ok()?;
+
+    // Drain the pipes while waiting, so a chatty command can't block on a full pipe
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if start_time.elapsed() < timeout => thread::sleep(POLL_INTERVAL),
+            Ok(None) => {
+                log::warn!(
+                    "Killing `{} {}` after it ran for {:?}",
+                    cmd,
+                    args.join(" "),
+                    start_time.elapsed()
+                );
+                kill(&mut child);
+                return None;
+            }
+            Err(err) => {
+                log::warn!("Unable to wait for `{} {}`: {}", cmd, args.join(" "), err);
+                return None;
+            }
+        }
+    };
+
+    log::debug!(
+        "`{} {}` took {:?} and exited with {}",
+        cmd,
+        args.join(" "),
+        start_time.elapsed(),
+        status
+    );
+
+    // Processes started by the command may still hold its pipes open
+    let deadline = start_time + timeout;
+    let read = |pipe: Receiver<String>| {
+        pipe.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .ok()
+    };
+    match (read(stdout), read(stderr)) {
+        (Some(stdout), Some(stderr)) => Some(CommandOutput {
+            status,
+            stdout,
+            stderr,
+        }),
+        _ => {
+            log::warn!(
+                "Dropping the output of `{} {}`, which was still open after {:?}",
+                cmd,
+                args.join(" "),
+                start_time.elapsed()
+            );
+            kill(&mut child);
+            None
+        }
+    }
+}
+
+/// Read a child's pipe to the end on a separate thread, sending its contents
+/// once it is closed
+fn read_pipe<R>(pipe: Option<R>) -> Receiver<String>
+where
+    R: Read + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buf).ok();
+        }
+        sender.send(String::from_utf8_lossy(&buf).into_owned()).ok();
+    });
+    receiver
+}
+
+/// Kill a child started by `exec_cmd`, along with the processes in its group
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `kill` has no memory safety requirements, and the negative pid
+        // only reaches the group that `exec_cmd` created for the child
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    child.kill().ok();
+    child.wait().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec_cmd_captures_output() {
+        let output = exec_cmd(
+            "sh",
+            &["-c", "echo out; echo err >&2"],
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+    }
+
+    #[test]
+    fn exec_cmd_reports_exit_status() {
+        let output = exec_cmd("sh", &["-c", "exit 3"], Duration::from_secs(5)).unwrap();
+        assert_eq!(output.status.code(), Some(3));
+    }
+
+    #[test]
+    fn exec_cmd_kills_command_after_timeout() {
+        let start_time = Instant::now();
+        assert!(exec_cmd("sleep", &["5"], Duration::from_millis(50)).is_none());
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn exec_cmd_does_not_wait_for_grandchildren() {
+        let start_time = Instant::now();
+        assert!(exec_cmd(
+            "sh",
+            &["-c", "sleep 5 & echo out"],
+            Duration::from_millis(200)
+        )
+        .is_none());
+        assert!(start_time.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn exec_cmd_missing_command() {
+        assert!(exec_cmd("starship-no-such-command", &[], Duration::from_secs(5)).is_none());
+    }
+}